@@ -0,0 +1,1109 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use ignore::WalkBuilder;
+use typst_syntax::ast::AstNode;
+use typst_syntax::package::PackageManifest;
+use typst_syntax::{LinkedNode, SyntaxKind, SyntaxNode, ast};
+
+use crate::{ANSII_BLUE, ANSII_RED, ANSII_YELLOW, Package, bundle_files};
+
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Finding {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Medium,
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn at(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Low => "note",
+            Severity::Medium => "warning",
+            Severity::High => "important",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+
+    pub fn color(self) -> &'static str {
+        match self {
+            Severity::Low => ANSII_BLUE,
+            Severity::Medium => ANSII_YELLOW,
+            Severity::High => ANSII_RED,
+        }
+    }
+}
+
+/// The number of lines shown before the line of a [`Location`].
+const SNIPPET_CONTEXT: usize = 2;
+
+/// A span inside a source file.
+pub struct Location {
+    /// The path relative to the package or project directory.
+    pub path: String,
+    /// The 1-based line number.
+    pub line: usize,
+    /// The 1-based column number.
+    pub column: usize,
+    /// The lines preceding the span and the line of the span itself.
+    lines: Vec<String>,
+    /// The character range of the span inside the last of the `lines`.
+    span: Range<usize>,
+}
+
+impl Location {
+    pub fn new(path: impl Into<String>, text: &str, range: Range<usize>) -> Self {
+        let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[range.start..]
+            .find('\n')
+            .map_or(text.len(), |i| range.start + i);
+        let line = text[..range.start].matches('\n').count() + 1;
+
+        let first_context_line = line.saturating_sub(SNIPPET_CONTEXT).max(1);
+        let lines = (text.lines())
+            .skip(first_context_line - 1)
+            .take(line - first_context_line + 1)
+            .map(|l| l.replace('\t', "    "))
+            .collect();
+
+        let width = |s: &str| {
+            s.chars()
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum::<usize>()
+        };
+        let span_start = width(&text[line_start..range.start]);
+        let span_end = width(&text[line_start..range.end.clamp(range.start, line_end)]);
+
+        Self {
+            path: path.into(),
+            line,
+            column: text[line_start..range.start].chars().count() + 1,
+            lines,
+            span: span_start..span_end.max(span_start + 1),
+        }
+    }
+
+    /// Render the location like a compiler diagnostic, with a caret under the span.
+    pub fn snippet(&self) -> String {
+        let Location {
+            path,
+            line,
+            column,
+            lines,
+            span,
+        } = self;
+        let gutter = line.to_string().len();
+
+        let mut buf = String::new();
+        _ = writeln!(buf, "{:gutter$}--> {path}:{line}:{column}", "");
+        _ = writeln!(buf, "{:gutter$} |", "");
+        let first_line = line + 1 - lines.len();
+        for (i, text) in lines.iter().enumerate() {
+            _ = writeln!(buf, "{:>gutter$} | {text}", first_line + i);
+        }
+        _ = write!(
+            buf,
+            "{:gutter$} | {:start$}{}",
+            "",
+            "",
+            "^".repeat(span.len()),
+            start = span.start
+        );
+        buf
+    }
+}
+
+pub fn check_package(
+    package: &Package,
+    manifest: &PackageManifest,
+) -> anyhow::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    check_description(manifest, &mut findings);
+    check_template_duplicates(package, manifest, &mut findings)?;
+    check_thumbnail(package, manifest, &mut findings)?;
+    check_foreign_artifacts(package, manifest, &mut findings)?;
+    check_placeholders(package, manifest, &mut findings)?;
+    check_network_resources(package, manifest, &mut findings)?;
+    check_unbounded_evaluation(package, manifest, &mut findings)?;
+    Ok(findings)
+}
+
+const DESCRIPTION_MIN_LEN: usize = 20;
+const DESCRIPTION_MAX_LEN: usize = 200;
+
+/// The description is displayed as plain text in the package list, so it
+/// should be a short sentence fragment without markup.
+fn check_description(manifest: &PackageManifest, findings: &mut Vec<Finding>) {
+    let name = manifest.package.name.as_str();
+    let Some(description) = &manifest.package.description else {
+        findings.push(Finding::new(
+            "missing `description`, add a short sentence fragment describing what the package does",
+        ));
+        return;
+    };
+    let description = description.trim();
+
+    let len = description.chars().count();
+    if len < DESCRIPTION_MIN_LEN {
+        findings.push(Finding::new(format!(
+            "`description` is too short ({len} characters), \
+             describe what the package does in at least {DESCRIPTION_MIN_LEN} characters"
+        )));
+    } else if len > DESCRIPTION_MAX_LEN {
+        findings.push(Finding::new(format!(
+            "`description` is too long ({len} characters), \
+             shorten it to at most {DESCRIPTION_MAX_LEN} characters and move details into the README"
+        )));
+    }
+
+    if description.ends_with('.') && !description.ends_with("...") {
+        findings.push(Finding::new(format!(
+            "`description` ends with a period, remove it: `{}`",
+            description.trim_end_matches('.')
+        )));
+    }
+
+    let simplify = |s: &str| {
+        (s.chars())
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let simple_description = simplify(description);
+    let simple_name = simplify(name);
+    if simple_description == simple_name
+        || simple_description == format!("the{simple_name}package")
+        || simple_description == format!("a{simple_name}package")
+        || simple_description == format!("{simple_name}package")
+    {
+        findings.push(Finding::new(format!(
+            "`description` just repeats the package name `{name}`, \
+             describe what the package does instead"
+        )));
+    }
+
+    if let Some(markup) = find_markup(description) {
+        findings.push(Finding::new(format!(
+            "`description` contains markup `{markup}`, \
+             it is displayed as plain text so write it without formatting"
+        )));
+    }
+}
+
+/// Find HTML tags, Markdown or Typst markup.
+fn find_markup(text: &str) -> Option<&str> {
+    // HTML tags, e.g. `<b>` or `</b>`.
+    for (i, _) in text.match_indices('<') {
+        let rest = &text[i + 1..];
+        let tag = rest.trim_start_matches('/');
+        if tag.starts_with(|c: char| c.is_ascii_alphabetic())
+            && let Some(end) = rest.find('>')
+        {
+            return Some(&text[i..i + 1 + end + 1]);
+        }
+    }
+
+    const MARKUP: &[&str] = &["`", "**", "__", "]("];
+    if let Some(markup) = MARKUP.iter().copied().find(|m| text.contains(m)) {
+        return Some(markup);
+    }
+
+    // Typst function calls, e.g. `#emph`, and Markdown headings, but not a
+    // `#` inside of words like `C#`.
+    for (i, _) in text.match_indices('#') {
+        let rest = &text[i + 1..];
+        let word_start = text[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || "([{".contains(c));
+        if !word_start {
+            continue;
+        }
+        if i == 0 && rest.starts_with(' ') {
+            return Some("#");
+        }
+        if rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(rest.len());
+            return Some(&text[i..i + 1 + len]);
+        }
+    }
+
+    // Math only comes in pairs, a single `$` is likely a price. A `$` directly
+    // followed by a digit also isn't math, e.g. `$5 to $10`.
+    let dollars = (text.match_indices('$'))
+        .map(|(i, _)| i)
+        .filter(|&i| !text[i + 1..].starts_with(|c: char| c.is_ascii_digit()))
+        .collect::<Vec<_>>();
+    if let [start, end, ..] = dollars[..] {
+        return Some(&text[start..end + 1]);
+    }
+
+    // Escape sequences like `\*`.
+    for (i, _) in text.match_indices('\\') {
+        if text[i + 1..].starts_with(|c: char| c.is_ascii_punctuation()) {
+            return Some(&text[i..i + 2]);
+        }
+    }
+
+    None
+}
+
+/// Templates should import the published package instead of shipping their
+/// own copy of the library files, otherwise scaffolded projects silently
+/// diverge from the package.
+fn check_template_duplicates(
+    package: &Package,
+    manifest: &PackageManifest,
+    findings: &mut Vec<Finding>,
+) -> anyhow::Result<()> {
+    let Some(template) = &manifest.template else {
+        return Ok(());
+    };
+    let package_dir = package.dir();
+    let template_dir = package_dir.join(template.path.trim_start_matches("./"));
+
+    // Split the bundle into library and template files.
+    let mut library_files = HashMap::new();
+    let mut template_files = Vec::new();
+    for path in bundle_files(&package_dir, manifest)? {
+        if path.extension().is_none_or(|e| e != "typ") {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        if path.starts_with(&template_dir) {
+            template_files.push((path, text));
+        } else if !text.trim().is_empty() {
+            library_files.insert(normalize(&text), path);
+        }
+    }
+    let library_names = (library_files.values())
+        .filter_map(|p| p.file_name())
+        .collect::<Vec<_>>();
+
+    let versionless_spec = package.versionless_spec();
+    let package_import = format!("{versionless_spec}:");
+    let imports_package = template_files.iter().any(|(_, text)| {
+        (imports(text).iter()).any(|(source, _)| source.starts_with(&package_import))
+    });
+
+    for (path, text) in template_files.iter() {
+        let relative_path = relative(path, &package_dir);
+        if let Some(library_path) = library_files.get(&normalize(text)) {
+            let library_path = relative(library_path, &package_dir);
+            findings.push(Finding::new(format!(
+                "template file `{relative_path}` is a copy of the package file `{library_path}`, \
+                 import `{versionless_spec}` instead"
+            )));
+        } else if !imports_package && path.file_name().is_some_and(|n| library_names.contains(&n)) {
+            findings.push(Finding::new(format!(
+                "template file `{relative_path}` seems to be a copy of a package file, \
+                 the template never imports `{versionless_spec}`"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Scaffolded projects must import the package at the version that is being
+/// published, version bumps often forget to update the template.
+pub fn check_scaffold(
+    package @ Package { vers, .. }: &Package,
+    scaffold_dir: &Path,
+    entrypoint: &Path,
+) -> anyhow::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let package_import = format!("{}:", package.versionless_spec());
+
+    let mut imports_package = false;
+    let walk = WalkBuilder::new(scaffold_dir)
+        .standard_filters(false)
+        .build();
+    for entry in walk.into_iter() {
+        let entry = entry.context("failed to traverse")?;
+        let path = entry.path();
+        if path.extension().is_none_or(|e| e != "typ") {
+            continue;
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+
+        for (source, range) in imports(&text) {
+            let Some(import_vers) = source.strip_prefix(&package_import) else {
+                continue;
+            };
+            imports_package = true;
+            if import_vers != *vers {
+                let relative_path = relative(path, scaffold_dir);
+                findings.push(
+                    Finding::new(format!(
+                        "scaffolded file imports `{source}`, but the package version is {vers}"
+                    ))
+                    .at(Location::new(relative_path, &text, range)),
+                );
+            }
+        }
+    }
+
+    if !imports_package {
+        let relative_path = relative(entrypoint, scaffold_dir);
+        findings.push(Finding::new(format!(
+            "scaffolded entrypoint `{relative_path}` never imports `{}`",
+            package.spec(),
+        )));
+    }
+
+    Ok(findings)
+}
+
+const THUMBNAIL_MAX_SIZE: u64 = 3 * 1024 * 1024;
+const LARGE_IMAGE_SIZE: u64 = 1024 * 1024;
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif", "svg"];
+
+/// Templates need a thumbnail, which is automatically excluded from the
+/// bundle, so it must not be referenced by the package. Other large preview
+/// images shouldn't be shipped in its place.
+fn check_thumbnail(
+    package: &Package,
+    manifest: &PackageManifest,
+    findings: &mut Vec<Finding>,
+) -> anyhow::Result<()> {
+    let package_dir = package.dir();
+    let bundle = bundle_files(&package_dir, manifest)?;
+
+    if let Some(template) = &manifest.template {
+        match &template.thumbnail {
+            None => {
+                findings.push(Finding::new(
+                    "template is missing a `thumbnail`, add a PNG or lossless WebP \
+                     image of one of the template's pages",
+                ));
+            }
+            Some(thumbnail) => {
+                let thumbnail = thumbnail.trim_start_matches("./");
+                let path = package_dir.join(thumbnail);
+                match std::fs::metadata(&path) {
+                    Err(_) => {
+                        findings.push(Finding::new(format!(
+                            "thumbnail `{thumbnail}` doesn't exist, \
+                             paths are relative to the package root"
+                        )));
+                    }
+                    Ok(metadata) if metadata.len() > THUMBNAIL_MAX_SIZE => {
+                        findings.push(Finding::new(format!(
+                            "thumbnail `{thumbnail}` is {}, it must not exceed {}, \
+                             try compressing it with `oxipng`",
+                            format_size(metadata.len()),
+                            format_size(THUMBNAIL_MAX_SIZE),
+                        )));
+                    }
+                    Ok(_) => (),
+                }
+                if !thumbnail.ends_with(".png") && !thumbnail.ends_with(".webp") {
+                    findings.push(Finding::new(format!(
+                        "thumbnail `{thumbnail}` must be a PNG or lossless WebP image"
+                    )));
+                }
+
+                // It isn't part of the bundle, so it can't be used.
+                let file_name = Path::new(thumbnail).file_name().unwrap_or_default();
+                let file_name = file_name.to_string_lossy();
+                for path in bundle.iter() {
+                    if path.extension().is_none_or(|e| e != "typ") {
+                        continue;
+                    }
+                    let text = std::fs::read_to_string(path)
+                        .with_context(|| format!("failed to read `{}`", path.display()))?;
+                    if let Some(start) = text.find(file_name.as_ref()) {
+                        let relative_path = relative(path, &package_dir);
+                        let range = start..start + file_name.len();
+                        findings.push(
+                            Finding::new(format!(
+                                "`{relative_path}` references the thumbnail `{thumbnail}`, \
+                                 which is excluded from the bundle",
+                            ))
+                            .at(Location::new(
+                                relative_path,
+                                &text,
+                                range,
+                            )),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    for path in bundle.iter() {
+        let is_image = path
+            .extension()
+            .is_some_and(|e| IMAGE_EXTENSIONS.iter().any(|i| e.eq_ignore_ascii_case(i)));
+        if !is_image {
+            continue;
+        }
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        if metadata.len() > LARGE_IMAGE_SIZE {
+            findings.push(Finding::new(format!(
+                "large image `{}` ({}) is shipped in the bundle, \
+                 if it's only a preview add it to `exclude`",
+                relative(path, &package_dir),
+                format_size(metadata.len()),
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Lockfiles of other package managers.
+const FOREIGN_LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "Cargo.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "flake.lock",
+];
+/// Directories of vendored dependencies or build artifacts.
+const FOREIGN_DIRS: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "__pycache__",
+    "site-packages",
+    "venv",
+];
+
+/// Artifacts of other ecosystems signal that the wrong directory was packaged.
+fn check_foreign_artifacts(
+    package: &Package,
+    manifest: &PackageManifest,
+    findings: &mut Vec<Finding>,
+) -> anyhow::Result<()> {
+    let package_dir = package.dir();
+    let mut reported_dirs = Vec::new();
+    for path in bundle_files(&package_dir, manifest)? {
+        let relative_path = path
+            .strip_prefix(&package_dir)
+            .expect("path to be relative to package dir");
+
+        let mut dir = PathBuf::new();
+        for component in relative_path.parent().into_iter().flat_map(|p| p.iter()) {
+            dir.push(component);
+            if FOREIGN_DIRS.iter().any(|d| component == *d) {
+                if !reported_dirs.contains(&dir) {
+                    findings.push(Finding::new(format!(
+                        "directory `{}` of another ecosystem is shipped in the bundle, \
+                         add it to `exclude` or check that the right directory was packaged",
+                        dir.display(),
+                    )));
+                    reported_dirs.push(dir.clone());
+                }
+                break;
+            }
+        }
+
+        if let Some(file_name) = path.file_name()
+            && FOREIGN_LOCKFILES.iter().any(|f| file_name == *f)
+        {
+            findings.push(Finding::new(format!(
+                "lockfile `{}` of another package manager is shipped in the bundle, \
+                 add it to `exclude` or check that the right directory was packaged",
+                relative_path.display(),
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Leftovers of templates or generators, matched case-insensitively, except
+/// for the all uppercase markers.
+const PLACEHOLDERS: &[&str] = &[
+    "TODO",
+    "FIXME",
+    "your name",
+    "your-name",
+    "your.email",
+    "author name",
+    "my-package",
+    "my_package",
+    "package-name",
+    "github.com/username",
+    "lorem ipsum",
+];
+/// Templates with less text than this, besides calls to `lorem`, are
+/// considered to only contain lorem ipsum.
+const LOREM_ONLY_TEXT_LEN: usize = 100;
+
+/// First-time submissions often contain leftover placeholders in the manifest,
+/// README or template.
+fn check_placeholders(
+    package: &Package,
+    manifest: &PackageManifest,
+    findings: &mut Vec<Finding>,
+) -> anyhow::Result<()> {
+    let package_dir = package.dir();
+    let mut files = vec![
+        package_dir.join("typst.toml"),
+        package_dir.join("README.md"),
+    ];
+    let mut template_entrypoint = None;
+    if let Some(template) = &manifest.template {
+        let template_dir = package_dir.join(template.path.trim_start_matches("./"));
+        template_entrypoint = Some(template_dir.join(template.entrypoint.as_str()));
+        for path in bundle_files(&package_dir, manifest)? {
+            let is_text = path.extension().is_some_and(|e| {
+                ["typ", "md", "txt", "bib", "yaml", "yml"]
+                    .iter()
+                    .any(|t| e == *t)
+            });
+            if path.starts_with(&template_dir) && is_text {
+                files.push(path);
+            }
+        }
+    }
+
+    let name = manifest.package.name.as_str();
+    for path in files.iter() {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let relative_path = relative(path, &package_dir);
+        // ASCII lowercase preserves byte offsets.
+        let lower_text = text.to_ascii_lowercase();
+        for placeholder in PLACEHOLDERS.iter() {
+            if *placeholder == name {
+                continue;
+            }
+            let is_marker = placeholder.chars().all(|c| c.is_ascii_uppercase());
+            let (haystack, needle) = if is_marker {
+                (&text, placeholder.to_string())
+            } else {
+                (&lower_text, placeholder.to_ascii_lowercase())
+            };
+            let mut matches = haystack.match_indices(&needle);
+            let Some((first, _)) = matches.next() else {
+                continue;
+            };
+            let more = match matches.count() {
+                0 => String::new(),
+                n => format!(" (and {n} more)"),
+            };
+            let range = first..first + needle.len();
+            findings.push(
+                Finding::new(format!(
+                    "contains the placeholder `{placeholder}`{more}, replace it with actual content"
+                ))
+                .at(Location::new(&relative_path, &text, range)),
+            );
+        }
+
+        if template_entrypoint.as_ref() == Some(path) && is_lorem_only(&text) {
+            findings.push(Finding::new(format!(
+                "template `{relative_path}` only contains lorem ipsum, \
+                 show how the package is used instead"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the document uses `lorem` and barely contains any other text.
+fn is_lorem_only(text: &str) -> bool {
+    let root = typst_syntax::parse(text);
+    let mut uses_lorem = false;
+    let mut text_len = 0;
+    visit(&LinkedNode::new(&root), &mut |node| {
+        if let Some(call) = node.get().cast::<ast::FuncCall>()
+            && let ast::Expr::Ident(callee) = call.callee()
+            && callee.as_str() == "lorem"
+        {
+            uses_lorem = true;
+        } else if node.kind() == SyntaxKind::Text {
+            text_len += node.text().trim().chars().count();
+        }
+    });
+    uses_lorem && text_len < LOREM_ONLY_TEXT_LEN
+}
+
+/// Functions that load files, typst can't fetch them from the network.
+const LOADING_FUNCS: &[&str] = &[
+    "image",
+    "read",
+    "json",
+    "csv",
+    "yaml",
+    "toml",
+    "xml",
+    "cbor",
+    "plugin",
+    "bibliography",
+];
+/// Words in comments that hint at assets which have to be downloaded.
+const DOWNLOAD_HINTS: &[&str] = &["download", "wget ", "curl "];
+
+/// Packages must be self-contained and compile offline, so flag URLs that are
+/// passed to loading functions and comments that instruct to download assets.
+fn check_network_resources(
+    package: &Package,
+    manifest: &PackageManifest,
+    findings: &mut Vec<Finding>,
+) -> anyhow::Result<()> {
+    let package_dir = package.dir();
+    for path in bundle_files(&package_dir, manifest)? {
+        if path.extension().is_none_or(|e| e != "typ") {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let relative_path = relative(&path, &package_dir);
+        let location = |range| Location::new(&relative_path, &text, range);
+
+        let root = typst_syntax::parse(&text);
+        visit(&LinkedNode::new(&root), &mut |node| {
+            if let Some(call) = node.get().cast::<ast::FuncCall>()
+                && let ast::Expr::Ident(callee) = call.callee()
+                && LOADING_FUNCS.contains(&callee.as_str())
+                && let Some(ast::Arg::Pos(ast::Expr::Str(arg))) = call.args().items().next()
+                && is_url(&arg.get())
+            {
+                let range =
+                    first_descendant(node, SyntaxKind::Str).map_or(node.range(), |n| n.range());
+                findings.push(
+                    Finding::new(format!(
+                        "passes the URL `{}` to `{}`, typst can't load remote files, \
+                         include the file in the package instead",
+                        arg.get(),
+                        callee.as_str(),
+                    ))
+                    .at(location(range)),
+                );
+            } else if let Some(import) = node.get().cast::<ast::ModuleImport>()
+                && let ast::Expr::Str(source) = import.source()
+                && is_url(&source.get())
+            {
+                let range =
+                    first_descendant(node, SyntaxKind::Str).map_or(node.range(), |n| n.range());
+                findings.push(
+                    Finding::new(format!(
+                        "imports the URL `{}`, typst can't load remote files",
+                        source.get(),
+                    ))
+                    .at(location(range)),
+                );
+            } else if matches!(
+                node.kind(),
+                SyntaxKind::LineComment | SyntaxKind::BlockComment
+            ) {
+                let comment = node.text().to_lowercase();
+                if let Some(hint) = DOWNLOAD_HINTS.iter().find(|h| comment.contains(*h)) {
+                    findings.push(
+                        Finding::new(format!(
+                            "comment mentions `{}`, packages must be self-contained \
+                             and can't depend on downloaded assets",
+                            hint.trim(),
+                        ))
+                        .at(location(first_line(node))),
+                    );
+                }
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Heuristically detect infinite loops and unconditional recursion in the
+/// top-level code of the entrypoints, which would make compilation hang.
+fn check_unbounded_evaluation(
+    package: &Package,
+    manifest: &PackageManifest,
+    findings: &mut Vec<Finding>,
+) -> anyhow::Result<()> {
+    let package_dir = package.dir();
+    let mut entrypoints = vec![package_dir.join(manifest.package.entrypoint.as_str())];
+    if let Some(template) = &manifest.template {
+        let template_dir = package_dir.join(template.path.as_str());
+        entrypoints.push(template_dir.join(template.entrypoint.as_str()));
+    }
+
+    for path in entrypoints {
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let relative_path = relative(&path, &package_dir);
+        find_unbounded_evaluation(&relative_path, &text, findings);
+    }
+
+    Ok(())
+}
+
+/// Check the top-level code of a single source file, see
+/// [`check_unbounded_evaluation`].
+fn find_unbounded_evaluation(path: &str, text: &str, findings: &mut Vec<Finding>) {
+    let location = |node: &LinkedNode| Location::new(path, text, first_line(node));
+
+    let root = typst_syntax::parse(text);
+    visit_top_level(&LinkedNode::new(&root), &mut |node| {
+        if let Some(while_loop) = node.get().cast::<ast::WhileLoop>()
+            && is_unbounded_loop(while_loop)
+        {
+            findings.push(
+                Finding::new("`while` loop never modifies its condition and never breaks")
+                    .at(location(node)),
+            );
+        } else if let Some(binding) = node.get().cast::<ast::LetBinding>()
+            && let Some(ast::Expr::Closure(closure)) = binding.init()
+            && let [name] = binding.kind().bindings()[..]
+            && calls_unconditionally(closure.body().to_untyped(), name.as_str())
+        {
+            findings.push(
+                Finding::new(format!(
+                    "function `{}` unconditionally calls itself",
+                    name.as_str(),
+                ))
+                .at(location(node)),
+            );
+        }
+    });
+}
+
+/// Visit nodes, but don't descend into function bodies.
+fn visit_top_level(node: &LinkedNode, f: &mut impl FnMut(&LinkedNode)) {
+    f(node);
+    if node.kind() == SyntaxKind::Closure {
+        return;
+    }
+    for child in node.children() {
+        visit_top_level(&child, f);
+    }
+}
+
+/// A loop is unbounded if it neither breaks nor returns, and none of the
+/// variables in the condition are modified in the body.
+fn is_unbounded_loop(while_loop: ast::WhileLoop) -> bool {
+    fn collect_idents(node: &SyntaxNode, idents: &mut Vec<String>) {
+        if let Some(ident) = node.cast::<ast::Ident>() {
+            idents.push(ident.as_str().to_string());
+        }
+        for child in node.children() {
+            collect_idents(child, idents);
+        }
+    }
+
+    fn exits_or_modifies(node: &SyntaxNode, idents: &[String]) -> bool {
+        let is_modified = |expr: ast::Expr| {
+            let mut expr = expr;
+            loop {
+                match expr {
+                    ast::Expr::Ident(ident) => return idents.iter().any(|i| i == ident.as_str()),
+                    ast::Expr::FieldAccess(access) => expr = access.target(),
+                    ast::Expr::FuncCall(call) => expr = call.callee(),
+                    _ => return false,
+                }
+            }
+        };
+
+        if matches!(node.kind(), SyntaxKind::LoopBreak | SyntaxKind::FuncReturn) {
+            return true;
+        }
+        if let Some(binary) = node.cast::<ast::Binary>()
+            && matches!(
+                binary.op(),
+                ast::BinOp::Assign
+                    | ast::BinOp::AddAssign
+                    | ast::BinOp::SubAssign
+                    | ast::BinOp::MulAssign
+                    | ast::BinOp::DivAssign
+            )
+            && is_modified(binary.lhs())
+        {
+            return true;
+        }
+        // Destructuring assignments like `(a, b) = (b, a + b)`.
+        if let Some(assignment) = node.cast::<ast::DestructAssignment>() {
+            let mut assigned = Vec::new();
+            collect_idents(assignment.pattern().to_untyped(), &mut assigned);
+            if assigned.iter().any(|a| idents.contains(a)) {
+                return true;
+            }
+        }
+        // Method calls like `stack.pop()` may mutate the variable.
+        if let Some(call) = node.cast::<ast::FuncCall>()
+            && let ast::Expr::FieldAccess(access) = call.callee()
+            && is_modified(access.target())
+        {
+            return true;
+        }
+        node.children().any(|c| exits_or_modifies(c, idents))
+    }
+
+    let mut condition_idents = Vec::new();
+    collect_idents(while_loop.condition().to_untyped(), &mut condition_idents);
+    !exits_or_modifies(while_loop.body().to_untyped(), &condition_idents)
+}
+
+/// Whether evaluating the node always calls the function `name`. Anything
+/// that is only evaluated conditionally is ignored.
+fn calls_unconditionally(node: &SyntaxNode, name: &str) -> bool {
+    if let Some(call) = node.cast::<ast::FuncCall>()
+        && let ast::Expr::Ident(callee) = call.callee()
+        && callee.as_str() == name
+    {
+        return true;
+    }
+    if let Some(conditional) = node.cast::<ast::Conditional>() {
+        return calls_unconditionally(conditional.condition().to_untyped(), name);
+    }
+    if let Some(while_loop) = node.cast::<ast::WhileLoop>() {
+        return calls_unconditionally(while_loop.condition().to_untyped(), name);
+    }
+    if let Some(for_loop) = node.cast::<ast::ForLoop>() {
+        return calls_unconditionally(for_loop.iterable().to_untyped(), name);
+    }
+    if let Some(binary) = node.cast::<ast::Binary>()
+        && matches!(binary.op(), ast::BinOp::And | ast::BinOp::Or)
+    {
+        return calls_unconditionally(binary.lhs().to_untyped(), name);
+    }
+    if node.kind() == SyntaxKind::Closure {
+        return false;
+    }
+    // Children are evaluated in order, so anything after a statement that
+    // might return early, like an `if` guard, is only evaluated conditionally.
+    for child in node.children() {
+        if calls_unconditionally(child, name) {
+            return true;
+        }
+        if may_exit_early(child) {
+            return false;
+        }
+    }
+    false
+}
+
+/// Whether a statement contains a `return` or `break` that isn't inside of a
+/// nested function.
+fn may_exit_early(node: &SyntaxNode) -> bool {
+    match node.kind() {
+        SyntaxKind::FuncReturn | SyntaxKind::LoopBreak => true,
+        SyntaxKind::Closure => false,
+        _ => node.children().any(may_exit_early),
+    }
+}
+
+fn is_url(s: &str) -> bool {
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Collect the sources of all string imports, e.g. `import "@preview/foo:0.1.0"`,
+/// and the range of the string literal.
+fn imports(text: &str) -> Vec<(String, Range<usize>)> {
+    let root = typst_syntax::parse(text);
+    let mut sources = Vec::new();
+    visit(&LinkedNode::new(&root), &mut |node| {
+        if let Some(import) = node.get().cast::<ast::ModuleImport>()
+            && let ast::Expr::Str(source) = import.source()
+        {
+            let range = first_descendant(node, SyntaxKind::Str).map_or(node.range(), |n| n.range());
+            sources.push((source.get().into(), range));
+        }
+    });
+    sources
+}
+
+/// Find the first descendant of a kind in pre-order.
+fn first_descendant<'a>(node: &LinkedNode<'a>, kind: SyntaxKind) -> Option<LinkedNode<'a>> {
+    for child in node.children() {
+        if child.kind() == kind {
+            return Some(child);
+        }
+        if let Some(descendant) = first_descendant(&child, kind) {
+            return Some(descendant);
+        }
+    }
+    None
+}
+
+/// The range of the first line of a node.
+fn first_line(node: &LinkedNode) -> Range<usize> {
+    let range = node.range();
+    let len = node.get().clone().into_text().find('\n');
+    range.start..len.map_or(range.end, |len| range.start + len)
+}
+
+/// Visit all nodes of the syntax tree in pre-order.
+fn visit(node: &LinkedNode, f: &mut impl FnMut(&LinkedNode)) {
+    f(node);
+    for child in node.children() {
+        visit(&child, f);
+    }
+}
+
+/// Ignore line endings and trailing whitespace when comparing files.
+fn normalize(text: &str) -> String {
+    let mut buf = String::with_capacity(text.len());
+    for line in text.trim().lines() {
+        buf.push_str(line.trim_end());
+        buf.push('\n');
+    }
+    buf
+}
+
+fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup() {
+        assert_eq!(find_markup("A **bold** package"), Some("**"));
+        assert_eq!(find_markup("Uses #emph for emphasis"), Some("#emph"));
+        assert_eq!(find_markup("# Heading"), Some("#"));
+        assert_eq!(find_markup("Solve $x^2$ quickly"), Some("$x^2$"));
+        assert_eq!(find_markup("Escaped \\* star"), Some("\\*"));
+        assert_eq!(find_markup("Formatted <b>text</b>"), Some("<b>"));
+    }
+
+    fn unbounded(code: &str) -> Vec<String> {
+        let mut findings = Vec::new();
+        find_unbounded_evaluation("main.typ", code, &mut findings);
+        findings.into_iter().map(|f| f.message).collect()
+    }
+
+    fn recursive(code: &str) -> bool {
+        unbounded(code)
+            .iter()
+            .any(|m| m.ends_with("unconditionally calls itself"))
+    }
+
+    fn infinite_loop(code: &str) -> bool {
+        unbounded(code)
+            .iter()
+            .any(|m| m.starts_with("`while` loop"))
+    }
+
+    #[test]
+    fn unconditional_recursion() {
+        assert!(recursive("#let f(n) = f(n - 1)"));
+        assert!(recursive("#let f(n) = n + f(n - 1)"));
+        assert!(recursive("#let f(n) = { let x = f(n); x }"));
+        assert!(recursive("#let f(n) = if f(n) { 1 } else { 2 }"));
+        assert!(recursive("#let f(n) = f(n) and n > 0"));
+        assert!(recursive("#let f(n) = { if n > 0 { [x] }; f(n - 1) }"));
+        assert!(recursive(
+            "#let f(n) = { for i in range(n) { [#i] }; f(n) }"
+        ));
+    }
+
+    #[test]
+    fn conditional_recursion() {
+        assert!(!recursive(
+            "#let fib(n) = { if n <= 1 { return n }; fib(n - 1) + fib(n - 2) }"
+        ));
+        assert!(!recursive(
+            "#let f(n) = if n <= 1 { 1 } else { n * f(n - 1) }"
+        ));
+        assert!(!recursive("#let f(n) = n > 0 and f(n - 1)"));
+        assert!(!recursive("#let f(n) = n == 0 or f(n - 1)"));
+        assert!(!recursive(
+            "#let f(n) = { while true { if n > 0 { return 1 } }; f(n) }"
+        ));
+        assert!(!recursive("#let f(n) = (x) => f(x)"));
+        assert!(!recursive("#let f(n) = { let g = () => f(n); g }"));
+        assert!(!recursive("#let f(n) = range(n).map(x => f(x))"));
+    }
+
+    #[test]
+    fn unbounded_loop() {
+        assert!(infinite_loop("#while true { [x] }"));
+        assert!(infinite_loop("#let i = 0\n#while i < 10 { [#i] }"));
+        assert!(infinite_loop(
+            "#let (i, j) = (0, 0)\n#while i < 10 { j += 1 }"
+        ));
+    }
+
+    #[test]
+    fn bounded_loop() {
+        assert!(!infinite_loop("#let i = 0\n#while i < 10 { i += 1 }"));
+        assert!(!infinite_loop("#let i = 0\n#while i < 10 { i = i + 1 }"));
+        assert!(!infinite_loop(
+            "#let stack = (1, 2)\n#while stack.len() > 0 { stack.pop() }"
+        ));
+        assert!(!infinite_loop("#while true { break }"));
+        assert!(!infinite_loop(
+            "#let (a, b) = (0, 1)\n#while a < 100 { (a, b) = (b, a + b) }"
+        ));
+        // Function bodies are only evaluated when called.
+        assert!(!infinite_loop("#let f() = { while true { [x] } }"));
+    }
+
+    #[test]
+    fn no_markup() {
+        assert_eq!(
+            find_markup("Fibonacci numbers for C# and Typst users"),
+            None
+        );
+        assert_eq!(find_markup("The #1 package for invoices"), None);
+        assert_eq!(find_markup("Invoices from $5 to $10"), None);
+        assert_eq!(find_markup("Costs only $5"), None);
+        assert_eq!(find_markup("Paths like C:\\Users"), None);
+        assert_eq!(find_markup("Compare a < b and b > c"), None);
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use typst_syntax::package::PackageManifest;
+
+use crate::diff::{FileChange, file_changes, unified_diff};
+use crate::render::{Renderer, Terminal};
+use crate::{
+    ANSII_BLUE, ANSII_CLEAR, ANSII_GREEN, ANSII_RED, ANSII_YELLOW, Log, Options, Package,
+    bundle_files, copy_files, example_files, example_prefix, http, parse_package, read_manifest,
+    render_previews, run_captured, run_command,
+};
+
+/// Packages are installed into a separate package directory and dependencies
+/// are downloaded into a separate cache, so the local packages stay untouched.
+struct Sandbox {
+    dir: PathBuf,
+    client: http::Client,
+}
+
+impl Sandbox {
+    fn typst_args(&self) -> Vec<OsString> {
+        vec![
+            "--package-path".into(),
+            self.dir.join("packages").into(),
+            "--package-cache-path".into(),
+            self.dir.join("cache").into(),
+        ]
+    }
+
+    /// Download the package from the package registry, so the comparison
+    /// matches what users get. The local checkout is only used if the version
+    /// isn't published or `--local` is passed.
+    fn install(
+        &self,
+        package @ Package {
+            namespace,
+            name,
+            vers,
+        }: &Package,
+        options: &Options,
+    ) -> anyhow::Result<Installed> {
+        let mut target_dir = self.dir.join("packages");
+        target_dir.extend([namespace, name, vers]);
+
+        let package_dir = package.dir();
+        if !options.local {
+            match self.download(package, &target_dir) {
+                Ok(manifest) => {
+                    return Ok(Installed {
+                        source_dir: target_dir.clone(),
+                        dir: target_dir,
+                        manifest,
+                    });
+                }
+                Err(e) if package_dir.exists() => {
+                    println!(
+                        "{ANSII_RED}failed to download{ANSII_CLEAR} `{name}:{vers}`, using the local checkout: {e}"
+                    );
+                    if target_dir.exists() {
+                        std::fs::remove_dir_all(&target_dir)
+                            .context("failed to remove partial download")?;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        } else if !package_dir.exists() {
+            bail!("`{name}:{vers}` doesn't exist in the local checkout");
+        }
+
+        println!(
+            "install {ANSII_YELLOW}{}{ANSII_CLEAR}",
+            package_dir.display()
+        );
+        let manifest = read_manifest(&package_dir)?;
+        let files = bundle_files(&package_dir, &manifest)?;
+        copy_files(&package_dir, files, &target_dir)?;
+        Ok(Installed {
+            dir: target_dir,
+            source_dir: package_dir,
+            manifest,
+        })
+    }
+
+    fn download(
+        &self,
+        Package {
+            namespace,
+            name,
+            vers,
+        }: &Package,
+        target_dir: &Path,
+    ) -> anyhow::Result<PackageManifest> {
+        let url = format!("https://packages.typst.org/{namespace}/{name}-{vers}.tar.gz");
+        let archive = self.dir.join(format!("{name}-{vers}.tar.gz"));
+        self.client.download(&url, &archive)?;
+        std::fs::create_dir_all(target_dir).context("failed to create package directory")?;
+        run_command(
+            &["tar"],
+            [
+                OsString::from("-xzf"),
+                archive.into(),
+                "-C".into(),
+                target_dir.into(),
+            ],
+        )?;
+        read_manifest(target_dir)
+    }
+}
+
+/// A package version installed into the sandbox.
+struct Installed {
+    /// The directory inside of the sandbox's package path.
+    dir: PathBuf,
+    /// The directory the package was installed from, which might contain
+    /// examples that are excluded from the bundle.
+    source_dir: PathBuf,
+    manifest: PackageManifest,
+}
+
+/// The result of comparing two package versions, which is rendered by a
+/// [`Renderer`].
+pub struct Comparison {
+    pub old: String,
+    pub new: String,
+    pub manifest_changes: Vec<ManifestChange>,
+    pub file_changes: Vec<(FileChange, String)>,
+    /// Line diffs of the modified text files by their path.
+    pub text_diffs: Vec<(String, String)>,
+    pub identical_pages: usize,
+    pub page_changes: Vec<PageChange>,
+    /// Captured output of the spawned `typst` processes.
+    pub log: Vec<String>,
+}
+
+/// A changed manifest field, `None` if the field doesn't exist.
+pub struct ManifestChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// A rendered page that differs between the versions.
+pub struct PageChange {
+    pub prefix: String,
+    pub page: u32,
+    pub status: &'static str,
+    /// The paths of the rendered images relative to the report.
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Rendered pages by their prefix, e.g. `template`, and page number.
+type Pages = BTreeMap<(String, u32), PathBuf>;
+
+pub fn compare_render(args: &[String], options: &Options) -> anyhow::Result<()> {
+    let [old, new] = args else {
+        bail!("expected two packages to compare, e.g. `foo:0.2.0 foo:0.3.0`");
+    };
+    let old = parse_package(old, &options.namespace)?;
+    let new = parse_package(new, &options.namespace)?;
+    let (old_label, new_label) = (label(&old), label(&new));
+    println!(
+        "Compare {ANSII_BLUE}{}{ANSII_CLEAR} and {ANSII_BLUE}{}{ANSII_CLEAR}",
+        old.spec(),
+        new.spec()
+    );
+    println!();
+
+    let dir = PathBuf::from_iter(["test", "compare", &format!("{old_label}_{new_label}")]);
+    if dir.exists() {
+        println!(
+            "remove existing comparison {ANSII_RED}{}{ANSII_CLEAR}",
+            dir.display()
+        );
+        std::fs::remove_dir_all(&dir).context("failed to remove existing comparison")?;
+    }
+    std::fs::create_dir_all(&dir).context("failed to create comparison directory")?;
+    let sandbox = Sandbox {
+        dir,
+        client: http::Client::new(options),
+    };
+
+    println!("=== Install ===");
+    let old_installed = sandbox.install(&old, options)?;
+    let new_installed = sandbox.install(&new, options)?;
+    let (old_dir, old_manifest) = (&old_installed.dir, &old_installed.manifest);
+    let (new_dir, new_manifest) = (&new_installed.dir, &new_installed.manifest);
+    println!();
+
+    println!("=== Render ===");
+    let mut log = Log::new(&old);
+    let old_pages = render(&sandbox, &old, &old_installed, &mut log, options)?;
+    let mut new_log = Log::new(&new);
+    let new_pages = render(&sandbox, &new, &new_installed, &mut new_log, options)?;
+    log.lines.extend(new_log.lines);
+    println!();
+
+    println!("=== Compare ===");
+    let manifest_changes = manifest_changes(old_manifest, new_manifest)?;
+    for ManifestChange { key, old, new } in manifest_changes.iter() {
+        let old = old.as_deref().unwrap_or("none");
+        let new = new.as_deref().unwrap_or("none");
+        println!("manifest {ANSII_YELLOW}{key}{ANSII_CLEAR}: {old} -> {new}");
+    }
+
+    let file_changes = file_changes(Some(old_dir), new_dir)?;
+    let mut text_diffs = Vec::new();
+    for (change, path) in file_changes.iter() {
+        let color = change.color();
+        println!("{color}{}{ANSII_CLEAR} {path}", change.as_str());
+
+        // Binary files, like images, are only compared by their pages.
+        if !matches!(change, FileChange::Modified) {
+            continue;
+        }
+        let (Ok(old_text), Ok(new_text)) = (
+            std::fs::read_to_string(old_dir.join(path)),
+            std::fs::read_to_string(new_dir.join(path)),
+        ) else {
+            continue;
+        };
+        let diff = unified_diff(&old_text, &new_text)
+            .unwrap_or_else(|| "file is too large to diff\n".to_string());
+        for line in diff.lines() {
+            let color = match line.as_bytes().first() {
+                Some(b'+') => ANSII_GREEN,
+                Some(b'-') => ANSII_RED,
+                Some(b'@') => ANSII_BLUE,
+                _ => "",
+            };
+            println!("  {color}{line}{ANSII_CLEAR}");
+        }
+        text_diffs.push((path.clone(), diff));
+    }
+
+    let keys = (old_pages.keys().chain(new_pages.keys())).collect::<BTreeSet<_>>();
+    let mut identical_pages = 0;
+    let mut page_changes = Vec::new();
+    for key @ (prefix, page) in keys {
+        let (old_page, new_page) = (old_pages.get(key), new_pages.get(key));
+        let status = match (old_page, new_page) {
+            (Some(a), Some(b)) if std::fs::read(a)? == std::fs::read(b)? => {
+                identical_pages += 1;
+                continue;
+            }
+            (Some(_), Some(_)) => "changed",
+            (Some(_), None) => "removed",
+            (None, Some(_)) => "added",
+            (None, None) => unreachable!(),
+        };
+        println!("{ANSII_YELLOW}{status}{ANSII_CLEAR} {prefix} page {page}");
+
+        // Relative to the report, which is written into the sandbox.
+        let image = |path: &PathBuf| {
+            let path = path.strip_prefix(&sandbox.dir).unwrap_or(path);
+            path.display().to_string()
+        };
+        page_changes.push(PageChange {
+            prefix: prefix.clone(),
+            page: *page,
+            status,
+            old: old_page.map(image),
+            new: new_page.map(image),
+        });
+    }
+    println!("{ANSII_GREEN}{identical_pages}{ANSII_CLEAR} identical pages");
+    println!();
+
+    let comparison = Comparison {
+        old: old.spec(),
+        new: new.spec(),
+        manifest_changes,
+        file_changes,
+        text_diffs,
+        identical_pages,
+        page_changes,
+        log: log.lines,
+    };
+
+    println!("=== Report ===");
+    print!("{}", Terminal.render_comparison(&comparison));
+    let report_path = sandbox
+        .dir
+        .join(format!("report.{}", options.format.extension()));
+    println!(
+        "write report {ANSII_GREEN}{}{ANSII_CLEAR}",
+        report_path.display()
+    );
+    let text = options.format.renderer().render_comparison(&comparison);
+    std::fs::write(&report_path, text).context("failed to write report")?;
+
+    Ok(())
+}
+
+fn label(Package { name, vers, .. }: &Package) -> String {
+    format!("{name}-{vers}")
+}
+
+/// Render the template and examples of a package, failures are only reported
+/// since older versions might not compile anymore.
+fn render(
+    sandbox: &Sandbox,
+    package: &Package,
+    Installed {
+        source_dir,
+        manifest,
+        ..
+    }: &Installed,
+    log: &mut Log,
+    options: &Options,
+) -> anyhow::Result<Pages> {
+    let version_dir = sandbox.dir.join(label(package));
+    let pages_dir = version_dir.join("pages");
+    let typst_args = sandbox.typst_args();
+
+    if let Some(template) = &manifest.template {
+        let spec = package.spec();
+        println!("initialize template {ANSII_GREEN}{spec}{ANSII_CLEAR}");
+        let project_dir = version_dir.join("project");
+        let mut args = vec![OsString::from("init")];
+        args.extend(typst_args.iter().cloned());
+        args.extend([spec.clone().into(), project_dir.clone().into()]);
+
+        let entrypoint = project_dir.join(template.entrypoint.as_str());
+        let res = run_captured(&options.typst, args, log).and_then(|_| {
+            render_previews(
+                &entrypoint,
+                None,
+                &typst_args,
+                &pages_dir,
+                "template",
+                log,
+                options,
+            )
+        });
+        if let Err(e) = res {
+            println!("{ANSII_RED}failed to render template{ANSII_CLEAR} `{spec}`: {e}");
+        }
+    }
+
+    // Examples are usually excluded from the bundle, so they're often only
+    // available if the package was installed from the local checkout.
+    if source_dir.exists() {
+        for example in example_files(source_dir)? {
+            let prefix = example_prefix(source_dir, &example);
+            let res = render_previews(
+                &example,
+                Some(source_dir),
+                &typst_args,
+                &pages_dir,
+                &prefix,
+                log,
+                options,
+            );
+            if let Err(e) = res {
+                println!(
+                    "{ANSII_RED}failed to render example{ANSII_CLEAR} `{}`: {e}",
+                    example.display()
+                );
+            }
+        }
+    }
+
+    let mut pages = Pages::new();
+    let Ok(entries) = std::fs::read_dir(&pages_dir) else {
+        return Ok(pages);
+    };
+    for entry in entries {
+        let path = entry.context("failed to read entry")?.path();
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        // Page numbers are zero-padded depending on the page count.
+        if let Some((prefix, page)) = stem.rsplit_once('-')
+            && let Ok(page) = page.parse()
+        {
+            pages.insert((prefix.to_string(), page), path);
+        }
+    }
+    Ok(pages)
+}
+
+fn manifest_changes(
+    old: &PackageManifest,
+    new: &PackageManifest,
+) -> anyhow::Result<Vec<ManifestChange>> {
+    fn flatten(prefix: &str, table: &toml::Table, fields: &mut BTreeMap<String, String>) {
+        for (key, value) in table.iter() {
+            let key = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{prefix}.{key}")
+            };
+            match value {
+                toml::Value::Table(table) => flatten(&key, table, fields),
+                value => _ = fields.insert(key, value.to_string()),
+            }
+        }
+    }
+
+    let mut old_fields = BTreeMap::new();
+    let mut new_fields = BTreeMap::new();
+    let old = toml::Table::try_from(old).context("failed to serialize manifest")?;
+    let new = toml::Table::try_from(new).context("failed to serialize manifest")?;
+    flatten("", &old, &mut old_fields);
+    flatten("", &new, &mut new_fields);
+
+    let keys = (old_fields.keys().chain(new_fields.keys())).collect::<BTreeSet<_>>();
+    let mut changes = Vec::new();
+    for key in keys {
+        let (old, new) = (old_fields.get(key), new_fields.get(key));
+        if old != new {
+            changes.push(ManifestChange {
+                key: key.clone(),
+                old: old.cloned(),
+                new: new.cloned(),
+            });
+        }
+    }
+    Ok(changes)
+}
@@ -0,0 +1,308 @@
+use std::io::{IsTerminal as _, Write as _};
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, bail};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ANSII_CLEAR, ANSII_GREEN, ANSII_RED, ANSII_YELLOW, Keep, Options, command, display_command,
+};
+
+/// The repository cloned by default, forks or mirrors can be entered instead.
+const PACKAGES_REPO: &str = "https://github.com/typst/packages";
+
+/// User configuration, which provides the defaults for the command line
+/// options. It's usually created using `init`.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Config {
+    /// The directory containing the `packages` checkout, the `test` directory
+    /// and the state. Commands are run inside of it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// The namespace of the reviewed packages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// The `typst` binary, optionally followed by arguments. This allows
+    /// using a toolchain manager, e.g. `["mise", "exec", "typst@0.13", "--", "typst"]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typst: Option<Vec<String>>,
+    /// The program used to open compiled PDFs, optionally followed by
+    /// arguments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewer: Option<Vec<String>>,
+    /// A token used to authenticate requests to the GitHub API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+    /// When to keep the scaffolded template projects after testing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep: Option<Keep>,
+    /// The maximum number of pages that are rendered to preview images.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_pages: Option<u32>,
+    /// Install reviewed packages into an isolated package path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<bool>,
+}
+
+impl Config {
+    fn path() -> anyhow::Result<PathBuf> {
+        let mut path = dirs::config_dir().context("failed to determine config directory")?;
+        path.extend(["typst-package-review", "config.toml"]);
+        Ok(path)
+    }
+
+    pub fn load() -> anyhow::Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = std::fs::read_to_string(&path).context("failed to read config")?;
+        toml::from_str(&text).with_context(|| format!("failed to parse `{}`", path.display()))
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path()?;
+        let dir = path.parent().expect("config file to have a parent");
+        std::fs::create_dir_all(dir).context("failed to create config directory")?;
+        let text = toml::to_string(self).context("failed to serialize config")?;
+
+        // The config might contain the GitHub token, so it must not be
+        // readable by others, even for a moment.
+        let mut open_options = std::fs::OpenOptions::new();
+        open_options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut open_options, 0o600);
+        let mut file = open_options.open(&path).context("failed to open config")?;
+        // The mode only applies to new files, so restrict existing ones
+        // before writing to them.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = std::fs::Permissions::from_mode(0o600);
+            file.set_permissions(permissions)
+                .context("failed to set config permissions")?;
+        }
+        file.write_all(text.as_bytes())
+            .context("failed to write config")?;
+
+        println!("write config {ANSII_GREEN}{}{ANSII_CLEAR}", path.display());
+        Ok(())
+    }
+
+    /// Override the default options.
+    pub fn apply(&self, options: &mut Options) {
+        if let Some(namespace) = &self.namespace {
+            options.namespace = namespace.clone();
+        }
+        if let Some(typst) = &self.typst {
+            options.typst = typst.clone();
+        }
+        if let Some(viewer) = &self.viewer {
+            options.viewer = viewer.clone();
+        }
+        if let Some(token) = &self.github_token {
+            options.github_token = Some(token.clone());
+        }
+        if let Some(keep) = self.keep {
+            options.keep = keep;
+        }
+        if let Some(preview_pages) = self.preview_pages {
+            options.preview_pages = preview_pages;
+        }
+        if let Some(sandbox) = self.sandbox {
+            options.sandbox = sandbox;
+        }
+    }
+}
+
+/// Interactively set up the config file, the current values are used as
+/// defaults.
+pub fn init() -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+
+    // The packages checkout.
+    let dir = match &config.dir {
+        Some(dir) => dir.clone(),
+        None => std::env::current_dir().context("failed to get current directory")?,
+    };
+    let dir = prompt(
+        "directory containing the `packages` checkout",
+        &dir.display().to_string(),
+    )?;
+    let dir = std::path::absolute(dir).context("invalid directory")?;
+    let packages_dir = dir.join("packages");
+    if !packages_dir.exists() {
+        let url = prompt("repository to clone into it, `-` to skip", PACKAGES_REPO)?;
+        if url != "-" {
+            println!(
+                "clone {ANSII_YELLOW}{}{ANSII_CLEAR}, this might take a while",
+                packages_dir.display()
+            );
+            Repository::clone(&url, &packages_dir).context("failed to clone packages")?;
+        }
+    }
+    config.dir = Some(dir);
+
+    let namespace_default = (config.namespace.clone()).unwrap_or(Options::default().namespace);
+    config.namespace = loop {
+        let namespace = prompt("namespace of the reviewed packages", &namespace_default)?;
+        if typst_syntax::is_ident(&namespace) {
+            break Some(namespace);
+        }
+        println!("{ANSII_RED}invalid namespace{ANSII_CLEAR} `{namespace}`");
+    };
+
+    // The typst binary, or a toolchain manager that runs it.
+    let default_typst = Options::default().typst;
+    let typst = prompt_command(
+        "typst binary or toolchain runner, e.g. `mise`",
+        config.typst.as_ref().unwrap_or(&default_typst),
+    )?;
+    match command(&typst).and_then(|mut cmd| Ok(cmd.arg("--version").output()?)) {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            println!("found {ANSII_GREEN}{}{ANSII_CLEAR}", version.trim());
+        }
+        _ => println!(
+            "{ANSII_RED}failed to run{ANSII_CLEAR} `{} --version`",
+            display_command(&typst)
+        ),
+    }
+    config.typst = Some(typst);
+
+    let default_viewer = Options::default().viewer;
+    let viewer = prompt_command(
+        "PDF viewer",
+        config.viewer.as_ref().unwrap_or(&default_viewer),
+    )?;
+    config.viewer = Some(viewer);
+
+    // Don't show an existing token.
+    let token_default = if config.github_token.is_some() {
+        "keep existing, `-` to remove"
+    } else {
+        "none"
+    };
+    let token = prompt_secret("GitHub token for the GitHub API", token_default)?;
+    match token.as_str() {
+        t if t == token_default => (),
+        "-" => config.github_token = None,
+        _ => config.github_token = Some(token),
+    }
+
+    config.sandbox = Some(confirm(
+        "install reviewed packages into an isolated package path in `test/sandbox` (y/n)",
+        config.sandbox.unwrap_or(false),
+    )?);
+
+    // The `test` directory, where templates are scaffolded and rendered.
+    let keep_default = match config.keep.unwrap_or(Keep::OnFailure) {
+        Keep::OnFailure => "on-failure",
+        Keep::Always => "always",
+        Keep::Never => "never",
+    };
+    config.keep = loop {
+        let keep = prompt(
+            "keep scaffolded projects after testing (on-failure, always, never)",
+            keep_default,
+        )?;
+        match keep.as_str() {
+            "on-failure" => break Some(Keep::OnFailure),
+            "always" => break Some(Keep::Always),
+            "never" => break Some(Keep::Never),
+            _ => println!("{ANSII_RED}invalid choice{ANSII_CLEAR} `{keep}`"),
+        }
+    };
+
+    let preview_pages_default = config
+        .preview_pages
+        .unwrap_or(Options::default().preview_pages);
+    config.preview_pages = loop {
+        let preview_pages = prompt(
+            "maximum number of pages rendered to preview images",
+            &preview_pages_default.to_string(),
+        )?;
+        match preview_pages.parse() {
+            Ok(v @ 1..) => break Some(v),
+            _ => println!("{ANSII_RED}invalid number{ANSII_CLEAR} `{preview_pages}`"),
+        }
+    };
+
+    config.save()
+}
+
+/// Ask for a value, an empty answer selects the default.
+fn prompt(question: &str, default: &str) -> anyhow::Result<String> {
+    print!("{question} [{ANSII_YELLOW}{default}{ANSII_CLEAR}]: ");
+    std::io::stdout()
+        .flush()
+        .context("failed to flush stdout")?;
+
+    let mut answer = String::new();
+    let read = (std::io::stdin().read_line(&mut answer)).context("failed to read stdin")?;
+    if read == 0 {
+        bail!("unexpected end of input");
+    }
+    let answer = answer.trim();
+    if answer.is_empty() {
+        Ok(default.to_string())
+    } else {
+        Ok(answer.to_string())
+    }
+}
+
+/// Like [`prompt`], but the answer isn't echoed, so secrets don't end up on
+/// the screen.
+fn prompt_secret(question: &str, default: &str) -> anyhow::Result<String> {
+    // `stty` changes the settings of the terminal connected to its stdin,
+    // which is inherited.
+    let echo = |on: bool| {
+        (Command::new("stty").arg(if on { "echo" } else { "-echo" }))
+            .status()
+            .is_ok_and(|s| s.success())
+    };
+    let hidden = std::io::stdin().is_terminal() && echo(false);
+    let answer = prompt(question, default);
+    if hidden {
+        echo(true);
+        println!();
+    }
+    answer
+}
+
+/// Ask for a program and its arguments separately, since the path of the
+/// program might contain whitespace.
+fn prompt_command(question: &str, default: &[String]) -> anyhow::Result<Vec<String>> {
+    let (program, args) = default
+        .split_first()
+        .expect("default command to have a program");
+    let program = prompt(question, program)?;
+    let args_default = if args.is_empty() {
+        "none".to_string()
+    } else {
+        args.join(" ")
+    };
+    let args = prompt(
+        "arguments passed to it, separated by whitespace, `-` for none",
+        &args_default,
+    )?;
+    let mut command = vec![program];
+    if args != "-" && args != "none" {
+        command.extend(args.split_whitespace().map(str::to_string));
+    }
+    Ok(command)
+}
+
+/// Ask a yes or no question.
+fn confirm(question: &str, default: bool) -> anyhow::Result<bool> {
+    loop {
+        let default = if default { "y" } else { "n" };
+        match prompt(question, default)?.to_ascii_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use ignore::WalkBuilder;
+use typst_syntax::package::{PackageManifest, PackageVersion};
+
+use crate::check::{Finding, Severity};
+use crate::{
+    ANSII_BLUE, ANSII_CLEAR, ANSII_GREEN, ANSII_RED, ANSII_YELLOW, Package, read_manifest,
+};
+
+pub enum FileChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl FileChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileChange::Added => "added",
+            FileChange::Removed => "removed",
+            FileChange::Modified => "modified",
+        }
+    }
+
+    pub fn color(&self) -> &'static str {
+        match self {
+            FileChange::Added => ANSII_GREEN,
+            FileChange::Removed => ANSII_RED,
+            FileChange::Modified => ANSII_YELLOW,
+        }
+    }
+}
+
+/// The changes of a package compared to its previous version in the local
+/// checkout.
+pub struct PackageDiff {
+    /// The previous version, `None` if this is the first version.
+    pub previous: Option<String>,
+    pub changes: Vec<(FileChange, String)>,
+}
+
+pub fn diff_package(package: &Package) -> anyhow::Result<PackageDiff> {
+    let package_dir = package.dir();
+    let Some(previous) = previous_version(package)? else {
+        let changes = file_changes(None, &package_dir)?;
+        return Ok(PackageDiff {
+            previous: None,
+            changes,
+        });
+    };
+    let previous_dir = package_dir.with_file_name(&previous);
+    let changes = file_changes(Some(&previous_dir), &package_dir)?;
+    Ok(PackageDiff {
+        previous: Some(previous),
+        changes,
+    })
+}
+
+/// The highest version in the local checkout that is lower than the version
+/// of the package.
+fn previous_version(package: &Package) -> anyhow::Result<Option<String>> {
+    let vers: PackageVersion = (package.vers.parse())
+        .map_err(|e| anyhow::anyhow!("invalid version `{}`: {e}", package.vers))?;
+    let package_dir = package.dir();
+    let versions_dir = package_dir.parent().expect("version dir to have a parent");
+
+    let mut previous = None;
+    let entries = std::fs::read_dir(versions_dir).context("failed to read package versions")?;
+    for entry in entries {
+        let entry = entry.context("failed to read entry")?;
+        let Some(other) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let Ok(other_vers) = other.parse::<PackageVersion>() else {
+            continue;
+        };
+        if other_vers < vers && previous.as_ref().is_none_or(|(v, _)| *v < other_vers) {
+            previous = Some((other_vers, other));
+        }
+    }
+    Ok(previous.map(|(_, dir)| dir))
+}
+
+pub fn print_diff(Package { name, vers, .. }: &Package, diff: &PackageDiff) {
+    let Some(previous) = &diff.previous else {
+        println!(
+            "{name}:{vers} first version with {ANSII_GREEN}{}{ANSII_CLEAR} files",
+            diff.changes.len()
+        );
+        return;
+    };
+    println!("{name}:{vers} changes since {ANSII_BLUE}{previous}{ANSII_CLEAR}");
+    if diff.changes.is_empty() {
+        println!("  no changes");
+    }
+    for (change, path) in diff.changes.iter() {
+        println!(
+            "  {}{}{ANSII_CLEAR} {path}",
+            change.color(),
+            change.as_str()
+        );
+    }
+}
+
+/// Flag changes that deserve attention when reviewing an update.
+pub fn check_diff(
+    package: &Package,
+    manifest: &PackageManifest,
+    diff: &PackageDiff,
+) -> anyhow::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let Some(previous) = &diff.previous else {
+        return Ok(findings);
+    };
+
+    let previous_manifest = read_manifest(&package.dir().with_file_name(previous))?;
+    if previous_manifest.package.license != manifest.package.license {
+        let license = |m: &PackageManifest| match &m.package.license {
+            Some(l) => format!("`{l}`"),
+            None => "none".to_string(),
+        };
+        let message = format!(
+            "license changed from {} to {}",
+            license(&previous_manifest),
+            license(manifest)
+        );
+        findings.push(Finding::new(message).severity(Severity::High));
+    }
+
+    for (change, path) in diff.changes.iter() {
+        let file_name = path.rsplit('/').next().unwrap_or(path).to_ascii_uppercase();
+        let is_license = file_name.starts_with("LICENSE") || file_name.starts_with("LICENCE");
+        let is_example = path.split('/').any(|c| c == "example" || c == "examples");
+        let is_source = path.ends_with(".typ");
+
+        match change {
+            FileChange::Modified | FileChange::Removed if is_license => {
+                let message = format!("license file `{path}` was {}", change.as_str());
+                findings.push(Finding::new(message).severity(Severity::High));
+            }
+            FileChange::Removed if is_source && !is_example => {
+                let message = format!("`{path}` was removed, which might break existing imports");
+                findings.push(Finding::new(message));
+            }
+            FileChange::Added if is_example && is_source => {
+                let message = format!("new example `{path}`");
+                findings.push(Finding::new(message).severity(Severity::Low));
+            }
+            _ => (),
+        }
+    }
+    Ok(findings)
+}
+
+/// Compare the files of two directories, if there is no old directory all
+/// files are added.
+pub fn file_changes(
+    old_dir: Option<&Path>,
+    new_dir: &Path,
+) -> anyhow::Result<Vec<(FileChange, String)>> {
+    fn files(dir: &Path) -> anyhow::Result<BTreeSet<PathBuf>> {
+        let mut files = BTreeSet::new();
+        for entry in WalkBuilder::new(dir).standard_filters(false).build() {
+            let entry = entry.context("failed to traverse")?;
+            if entry.file_type().is_some_and(|f| f.is_file()) {
+                let path = entry.path().strip_prefix(dir).expect("path inside dir");
+                files.insert(path.to_path_buf());
+            }
+        }
+        Ok(files)
+    }
+
+    let old_files = match old_dir {
+        Some(old_dir) => files(old_dir)?,
+        None => BTreeSet::new(),
+    };
+    let new_files = files(new_dir)?;
+    let mut changes = Vec::new();
+    for path in old_files.union(&new_files) {
+        let change = match (old_dir, old_files.contains(path), new_files.contains(path)) {
+            (Some(old_dir), true, true) => {
+                let old = std::fs::read(old_dir.join(path)).context("failed to read file")?;
+                let new = std::fs::read(new_dir.join(path)).context("failed to read file")?;
+                if old == new {
+                    continue;
+                }
+                FileChange::Modified
+            }
+            (_, true, false) => FileChange::Removed,
+            _ => FileChange::Added,
+        };
+        changes.push((change, path.display().to_string()));
+    }
+    Ok(changes)
+}
+
+/// The number of unchanged lines shown around changed lines.
+const DIFF_CONTEXT: usize = 3;
+/// The maximum size of the table used by [`unified_diff`], which grows with
+/// the product of the changed lines of both files.
+const DIFF_MAX_CELLS: usize = 16_000_000;
+
+/// A line diff in the unified format, `None` if the files are too large.
+pub fn unified_diff(old: &str, new: &str) -> Option<String> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // Skip the common prefix and suffix, which is usually most of the file.
+    let prefix = (old.iter().zip(new.iter()))
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = (old[prefix..].iter().rev().zip(new[prefix..].iter().rev()))
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    if (a.len() + 1) * (b.len() + 1) > DIFF_MAX_CELLS {
+        return None;
+    }
+
+    // The length of the longest common subsequence of `a[i..]` and `b[j..]`.
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    // Lines prefixed with ` `, `-` or `+`.
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    lines.extend(old[..prefix].iter().map(|l| (' ', *l)));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push((' ', a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len()
+            || (i < a.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            lines.push(('-', a[i]));
+            i += 1;
+        } else {
+            lines.push(('+', b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| (' ', *l)));
+
+    // Group the changed lines and their context into hunks.
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    for (k, _) in lines.iter().enumerate().filter(|(_, (tag, _))| *tag != ' ') {
+        let start = k.saturating_sub(DIFF_CONTEXT);
+        let end = (k + 1 + DIFF_CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    let mut buf = String::new();
+    let (mut old_line, mut new_line) = (0, 0);
+    let mut k = 0;
+    for hunk in hunks {
+        for (tag, _) in lines[k..hunk.start].iter() {
+            old_line += (*tag != '+') as usize;
+            new_line += (*tag != '-') as usize;
+        }
+        k = hunk.start;
+        let hunk = &lines[hunk];
+        let old_len = hunk.iter().filter(|(tag, _)| *tag != '+').count();
+        let new_len = hunk.iter().filter(|(tag, _)| *tag != '-').count();
+        // Empty ranges start at the line before them.
+        let start = |line: usize, len: usize| if len == 0 { line } else { line + 1 };
+        _ = writeln!(
+            buf,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            start(old_line, old_len),
+            start(new_line, new_len)
+        );
+        for (tag, line) in hunk.iter() {
+            _ = writeln!(buf, "{tag}{line}");
+        }
+    }
+    Some(buf)
+}
+
+/// Typst Universe doesn't publish download statistics, so the other packages
+/// in the local checkout that import the package are used to gauge how widely
+/// it is used.
+pub fn dependents(
+    package @ Package {
+        namespace, name, ..
+    }: &Package,
+) -> anyhow::Result<Vec<String>> {
+    let namespace_dir = PathBuf::from_iter(["packages", "packages", namespace]);
+    let import = format!("\"{}:", package.versionless_spec());
+
+    let mut dependents = Vec::new();
+    let Ok(entries) = std::fs::read_dir(&namespace_dir) else {
+        return Ok(dependents);
+    };
+    for entry in entries {
+        let entry = entry.context("failed to read entry")?;
+        let Some(dependent) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if dependent != *name && imports_package(&entry.path(), &import)? {
+            dependents.push(dependent);
+        }
+    }
+    dependents.sort();
+    Ok(dependents)
+}
+
+/// Whether any source file in the directory contains the import, the search
+/// stops at the first match.
+fn imports_package(dir: &Path, import: &str) -> anyhow::Result<bool> {
+    for entry in WalkBuilder::new(dir).build() {
+        let entry = entry.context("failed to traverse")?;
+        let path = entry.path();
+        if path.extension().is_none_or(|e| e != "typ") {
+            continue;
+        }
+        if std::fs::read_to_string(path).is_ok_and(|text| text.contains(import)) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_hunks() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n").unwrap(), "");
+        assert_eq!(
+            unified_diff("a\nb\nc\n", "a\nB\nc\n").unwrap(),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+        assert_eq!(
+            unified_diff("a\n", "a\nb\nc\n").unwrap(),
+            "@@ -1,1 +1,3 @@\n a\n+b\n+c\n"
+        );
+        assert_eq!(unified_diff("a\n", "").unwrap(), "@@ -1,1 +0,0 @@\n-a\n");
+    }
+
+    #[test]
+    fn distant_changes_are_separate_hunks() {
+        let old = (1..=20).map(|i| format!("{i}\n")).collect::<String>();
+        let new = (old.replacen("2\n", "two\n", 1)).replace("18\n", "eighteen\n");
+        assert_eq!(
+            unified_diff(&old, &new).unwrap(),
+            "@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+             @@ -15,6 +15,6 @@\n 15\n 16\n 17\n-18\n+eighteen\n 19\n 20\n"
+        );
+    }
+}
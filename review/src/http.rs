@@ -0,0 +1,171 @@
+use std::cell::Cell;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, bail};
+
+use crate::{ANSII_BLUE, ANSII_CLEAR, Options};
+
+const GITHUB_API: &str = "https://api.github.com/";
+
+/// Fetches URLs using `curl`, while being polite to the servers: requests are
+/// spaced out by a minimum interval and responses are cached on disk, so long
+/// review sessions don't run into rate limits. The time of the last request is
+/// stored in the cache directory, so consecutive runs are spaced out as well.
+pub struct Client {
+    interval: Duration,
+    cache_ttl: Duration,
+    cache_dir: Option<PathBuf>,
+    last_request: Cell<Option<SystemTime>>,
+    github_token: Option<String>,
+}
+
+impl Client {
+    pub fn new(options: &Options) -> Self {
+        let cache_dir = dirs::cache_dir().map(|d| d.join("typst-package-review"));
+        Self {
+            interval: Duration::from_millis(options.request_interval),
+            cache_ttl: Duration::from_secs(options.cache_ttl),
+            cache_dir,
+            last_request: Cell::new(None),
+            github_token: options.github_token.clone(),
+        }
+    }
+
+    /// Fetch a URL, or return the cached response if it's recent enough.
+    pub fn get(&self, url: &str) -> anyhow::Result<String> {
+        let cache_path = self.cache_path("http", url);
+        if let Some(path) = &cache_path
+            && let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified())
+            && modified.elapsed().is_ok_and(|age| age < self.cache_ttl)
+        {
+            println!("cached {ANSII_BLUE}{url}{ANSII_CLEAR}");
+            return std::fs::read_to_string(path).context("failed to read cached response");
+        }
+
+        println!("fetching {ANSII_BLUE}{url}{ANSII_CLEAR}");
+        self.wait();
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--fail", "--location"]);
+        let token = (self.github_token.as_ref()).filter(|_| url.starts_with(GITHUB_API));
+        if token.is_some() {
+            // Pass the header through stdin, so the token doesn't show up in
+            // the process list.
+            cmd.args(["--header", "@-"]);
+        }
+        let mut child = (cmd.arg(url))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to execute `curl`")?;
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        if let Some(token) = token {
+            writeln!(stdin, "Authorization: Bearer {token}").context("failed to write header")?;
+        }
+        drop(stdin);
+        let output = child
+            .wait_with_output()
+            .context("failed to execute `curl`")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("failed to fetch `{url}`: {}", stderr.trim());
+        }
+
+        let text = String::from_utf8(output.stdout)
+            .with_context(|| format!("invalid utf-8 in response of `{url}`"))?;
+        if let Some(path) = &cache_path {
+            // The cache is only an optimization, so failing to write it is fine.
+            _ = std::fs::create_dir_all(path.parent().expect("cache file to have a parent"))
+                .and_then(|_| std::fs::write(path, &text));
+        }
+        Ok(text)
+    }
+
+    /// Download a URL to a file. Downloads are cached regardless of their age,
+    /// since published package archives never change.
+    pub fn download(&self, url: &str, path: &Path) -> anyhow::Result<()> {
+        let cache_path = self.cache_path("downloads", url);
+        if let Some(cache_path) = &cache_path
+            && cache_path.exists()
+        {
+            println!("cached {ANSII_BLUE}{url}{ANSII_CLEAR}");
+            std::fs::copy(cache_path, path).context("failed to copy cached download")?;
+            return Ok(());
+        }
+
+        println!("download {ANSII_BLUE}{url}{ANSII_CLEAR}");
+        self.wait();
+        let output = Command::new("curl")
+            .args([
+                "--silent",
+                "--show-error",
+                "--fail",
+                "--location",
+                "--output",
+            ])
+            .arg(path)
+            .arg(url)
+            .output()
+            .context("failed to execute `curl`")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("failed to download `{url}`: {}", stderr.trim());
+        }
+        if let Some(cache_path) = &cache_path {
+            // The cache is only an optimization, so failing to write it is fine.
+            _ = std::fs::create_dir_all(cache_path.parent().expect("cache file to have a parent"))
+                .and_then(|_| std::fs::copy(path, cache_path));
+        }
+        Ok(())
+    }
+
+    /// Wait until at least [`Options::request_interval`] has passed since the
+    /// last request of this or a previous run.
+    fn wait(&self) {
+        let last_request_path = (self.cache_dir.as_ref()).map(|d| d.join("last-request"));
+        let persisted = (last_request_path.as_ref())
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|millis| millis.trim().parse().ok())
+            .map(|millis| SystemTime::UNIX_EPOCH + Duration::from_millis(millis));
+        let last = self.last_request.get().max(persisted);
+        if let Some(last) = last {
+            // A time in the future, e.g. after the clock was changed, counts
+            // as just now.
+            let elapsed = last.elapsed().unwrap_or_default();
+            if elapsed < self.interval {
+                std::thread::sleep(self.interval - elapsed);
+            }
+        }
+
+        let now = SystemTime::now();
+        self.last_request.set(Some(now));
+        if let Some(path) = &last_request_path
+            && let Ok(since_epoch) = now.duration_since(SystemTime::UNIX_EPOCH)
+        {
+            // Like the cache, this is best effort.
+            _ = std::fs::create_dir_all(path.parent().expect("file to have a parent"))
+                .and_then(|_| std::fs::write(path, since_epoch.as_millis().to_string()));
+        }
+    }
+
+    fn cache_path(&self, kind: &str, url: &str) -> Option<PathBuf> {
+        if self.cache_ttl.is_zero() {
+            return None;
+        }
+        let name = (url.chars())
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        Some(self.cache_dir.as_ref()?.join(kind).join(name))
+    }
+}
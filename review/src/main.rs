@@ -3,11 +3,29 @@ use git2::build::CheckoutBuilder;
 use git2::{BranchType, FetchOptions, Repository};
 use ignore::WalkBuilder;
 use ignore::overrides::OverrideBuilder;
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
 use std::fmt::Write as _;
 use std::path::{Path, PathBuf};
 use std::process::{Command, ExitCode};
 use typst_syntax::package::PackageManifest;
 
+use crate::config::Config;
+use crate::render::Renderer;
+use crate::report::Report;
+use crate::state::State;
+
+mod check;
+mod compare;
+mod config;
+mod diff;
+mod http;
+mod removal;
+mod render;
+mod report;
+mod state;
+mod universe;
+
 const ANSII_RED: &str = "\x1b[31m";
 const ANSII_GREEN: &str = "\x1b[32m";
 const ANSII_YELLOW: &str = "\x1b[33m";
@@ -23,7 +41,7 @@ impl Args<'_> {
     fn branch_name(&self) -> String {
         let Args { packages, pr_nr } = self;
         let mut buf = String::new();
-        for (i, Package { name, vers }) in packages.iter().enumerate() {
+        for (i, Package { name, vers, .. }) in packages.iter().enumerate() {
             if i > 0 {
                 buf.push(',');
             }
@@ -36,14 +54,35 @@ impl Args<'_> {
 
 #[derive(Debug)]
 struct Package<'a> {
+    namespace: &'a str,
     name: &'a str,
     vers: &'a str,
 }
 
 impl Package<'_> {
     fn spec(&self) -> String {
-        let Package { name, vers } = self;
-        format!("@preview/{name}:{vers}")
+        let Package {
+            namespace,
+            name,
+            vers,
+        } = self;
+        format!("@{namespace}/{name}:{vers}")
+    }
+
+    fn versionless_spec(&self) -> String {
+        let Package {
+            namespace, name, ..
+        } = self;
+        format!("@{namespace}/{name}")
+    }
+
+    fn dir(&self) -> PathBuf {
+        let Package {
+            namespace,
+            name,
+            vers,
+        } = self;
+        PathBuf::from_iter(["packages", "packages", namespace, name, vers])
     }
 }
 
@@ -55,6 +94,67 @@ fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
+struct Options {
+    /// The namespace of the reviewed packages.
+    namespace: String,
+    /// The maximum number of pages that are rendered to preview images.
+    preview_pages: u32,
+    /// Compare the packages against the published packages on Typst Universe.
+    universe: bool,
+    /// Clean all local packages, not only the ones installed by this tool.
+    all: bool,
+    /// Install compared versions from the local checkout instead of the
+    /// package registry.
+    local: bool,
+    /// Install reviewed packages into an isolated package path inside of the
+    /// `test` directory, instead of the local packages.
+    sandbox: bool,
+    /// When to keep the scaffolded template projects after testing.
+    keep: Keep,
+    /// The minimum time between HTTP requests in milliseconds.
+    request_interval: u64,
+    /// How long HTTP responses are cached in seconds, `0` disables the cache.
+    cache_ttl: u64,
+    /// The format of the written report.
+    format: render::Format,
+    /// The `typst` binary and arguments passed before the `typst` ones.
+    typst: Vec<String>,
+    /// The program used to open compiled PDFs and its arguments.
+    viewer: Vec<String>,
+    /// A token used to authenticate requests to the GitHub API.
+    github_token: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Keep {
+    /// Only keep the projects of packages that failed to test or have
+    /// warnings or important findings.
+    OnFailure,
+    Always,
+    Never,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            namespace: "preview".to_string(),
+            preview_pages: 10,
+            universe: false,
+            all: false,
+            local: false,
+            sandbox: false,
+            keep: Keep::OnFailure,
+            request_interval: 1000,
+            cache_ttl: 600,
+            format: render::Format::Markdown,
+            typst: vec!["typst".to_string()],
+            viewer: vec!["xdg-open".to_string()],
+            github_token: None,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Cmd {
     Review,
@@ -84,16 +184,27 @@ fn run() -> anyhow::Result<()> {
     let Some(cmd) = args.next() else {
         bail!("missing command");
     };
+    if cmd == "init" {
+        return config::init();
+    }
+
+    let config = Config::load()?;
+    if let Some(dir) = &config.dir {
+        std::env::set_current_dir(dir)
+            .with_context(|| format!("failed to enter `{}`", dir.display()))?;
+    }
+    let (options, args) = parse_options(&config, args)?;
 
     let cmd = match cmd.as_str() {
         "review" => Cmd::Review,
         "fetch" => Cmd::Fetch,
         "install" => Cmd::Install,
-        "clean" => return clean(),
+        "clean" => return clean(&options),
+        "compare-render" => return compare::compare_render(&args, &options),
         _ => bail!("unknown command `{cmd}`"),
     };
 
-    let args = args.collect::<Vec<_>>().join(" ");
+    let args = args.join(" ");
     let args: Vec<_> = args
         .split(' ')
         .flat_map(|s| {
@@ -105,11 +216,11 @@ fn run() -> anyhow::Result<()> {
         })
         .filter(|s| !s.is_empty())
         .collect();
-    let args = parse_args(&args)?;
+    let args = parse_args(&args, &options.namespace)?;
 
     let Args { packages, pr_nr } = &args;
     println!("PR {ANSII_YELLOW}#{pr_nr}{ANSII_CLEAR}");
-    for Package { name, vers } in packages.iter() {
+    for Package { name, vers, .. } in packages.iter() {
         println!("  {ANSII_BLUE}{name}{ANSII_CLEAR} v{vers}");
     }
     println!();
@@ -122,26 +233,194 @@ fn run() -> anyhow::Result<()> {
 
     let mut res = Ok(());
     if cmd.install() {
+        let mut state = State::load()?;
+        let mut report = Report::new(*pr_nr);
+        let client = http::Client::new(&options);
+
+        // Removed packages can't be installed or tested.
+        let (removed, packages): (Vec<_>, Vec<_>) =
+            packages.iter().partition(|p| removal::is_removed(p));
+        if !removed.is_empty() {
+            println!("=== Removal ===");
+            let pr = removal::PullRequest::fetch(&client, *pr_nr)
+                .inspect_err(|e| println!("{ANSII_RED}failed to fetch PR{ANSII_CLEAR}: {e}"))
+                .ok();
+            let base = removal::base_commit()
+                .inspect_err(|e| {
+                    println!("{ANSII_RED}failed to find the base commit{ANSII_CLEAR}: {e}")
+                })
+                .ok();
+            for package in removed.iter() {
+                let findings = removal::check_removal(package, base, pr.as_ref())?;
+                render::print_findings(package, &findings);
+                report.set_removed(package);
+                report.add(package, findings);
+            }
+            println!();
+        }
+
         println!("=== Install ===");
-        let manifests = (packages.iter())
-            .map(install_package)
-            .collect::<Result<Vec<_>, _>>()?;
+        let mut manifests = Vec::with_capacity(packages.len());
+        for package in packages.iter() {
+            let manifest = install_package(package, &mut state, &options);
+            // Remember installed packages, even if a later one fails.
+            state.save()?;
+            manifests.push(manifest?);
+        }
         println!();
 
+        println!("=== Diff ===");
+        let mut diffs = Vec::with_capacity(packages.len());
+        for package in packages.iter() {
+            let diff = diff::diff_package(package)?;
+            diff::print_diff(package, &diff);
+            diffs.push(diff);
+        }
+        println!();
+
+        println!("=== Check ===");
+        for ((package, manifest), diff) in packages.iter().zip(manifests.iter()).zip(diffs) {
+            if diff.previous.is_some() {
+                let dependents = diff::dependents(package)?;
+                println!(
+                    "used by {}",
+                    render::dependents_str(&dependents, |d| d.into())
+                );
+                report.set_dependents(package, dependents);
+            }
+            let mut findings = diff::check_diff(package, manifest, &diff)?;
+            findings.extend(check::check_package(package, manifest)?);
+            render::print_findings(package, &findings);
+            report.add(package, findings);
+            report.set_diff(package, diff);
+        }
+        println!();
+
+        if options.universe {
+            println!("=== Universe ===");
+            match universe::Index::fetch(&client, &options.namespace) {
+                Ok(index) => {
+                    for package in packages.iter() {
+                        let versions = (index.versions(package.name).iter())
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>();
+                        let url = universe::page_url(package).filter(|_| !versions.is_empty());
+                        universe::print_published(&versions, url.as_deref());
+                        report.set_published(package, versions, url);
+
+                        let findings = universe::check_package(package, &index);
+                        render::print_findings(package, &findings);
+                        report.add(package, findings);
+                    }
+                }
+                Err(e) => println!("{ANSII_RED}failed to fetch package index{ANSII_CLEAR}: {e}"),
+            }
+            println!();
+        }
+
         println!("=== Test ===");
         std::fs::create_dir_all("test").context("failed to create `test` directory")?;
         for (package, manifest) in packages.iter().zip(manifests.iter()) {
-            let r = test_package(package, manifest);
+            let mut log = Log::new(package);
+            let r = test_package(
+                package,
+                manifest,
+                *pr_nr,
+                &mut state,
+                &mut report,
+                &mut log,
+                &options,
+            );
+            report.add_log(package, log.lines);
+            let success = r.is_ok() && !report.needs_attention(package);
+            remove_artifacts(package, success, &options)?;
             if res.is_ok() {
                 res = r;
             }
         }
+        state.save()?;
+        println!();
+
+        println!("=== Report ===");
+        print!("{}", render::Terminal.render(&report));
+        report.write(options.format)?;
     }
 
     res
 }
 
-fn parse_args<'a>(args: &[&'a str]) -> anyhow::Result<Args<'a>> {
+/// Split off `--option value` and `--option=value` pairs from the positional arguments.
+fn parse_options(
+    config: &Config,
+    mut args: impl Iterator<Item = String>,
+) -> anyhow::Result<(Options, Vec<String>)> {
+    let mut options = Options::default();
+    config.apply(&mut options);
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        let Some(option) = arg.strip_prefix("--") else {
+            positional.push(arg);
+            continue;
+        };
+        let (option, inline_value) = match option.split_once('=') {
+            Some((o, v)) => (o, Some(v.to_string())),
+            None => (option, None),
+        };
+        let value = || {
+            inline_value
+                .or_else(|| args.next())
+                .with_context(|| format!("missing value for `--{option}`"))
+        };
+
+        match option {
+            "namespace" => {
+                let v = value()?;
+                if !typst_syntax::is_ident(&v) {
+                    bail!("namespace is not valid - `{v}`");
+                }
+                options.namespace = v;
+            }
+            "preview-pages" => {
+                let v = value()?;
+                let Ok(v @ 1..) = v.parse() else {
+                    bail!("invalid number of preview pages, expected at least 1 - `{v}`");
+                };
+                options.preview_pages = v;
+            }
+            "request-interval" => {
+                let v = value()?;
+                let Ok(v) = v.parse() else {
+                    bail!("invalid request interval - `{v}`");
+                };
+                options.request_interval = v;
+            }
+            "cache-ttl" => {
+                let v = value()?;
+                let Ok(v) = v.parse() else {
+                    bail!("invalid cache TTL - `{v}`");
+                };
+                options.cache_ttl = v;
+            }
+            "format" => {
+                let v = value()?;
+                let Some(v) = render::Format::from_name(&v) else {
+                    bail!("unknown report format - `{v}`");
+                };
+                options.format = v;
+            }
+            "universe" => options.universe = true,
+            "all" => options.all = true,
+            "local" => options.local = true,
+            "sandbox" => options.sandbox = true,
+            "keep-artifacts" => options.keep = Keep::Always,
+            "no-keep" => options.keep = Keep::Never,
+            _ => bail!("unknown option `--{option}`"),
+        }
+    }
+    Ok((options, positional))
+}
+
+fn parse_args<'a>(args: &[&'a str], namespace: &'a str) -> anyhow::Result<Args<'a>> {
     if args.len() < 2 {
         bail!("expected at least one package and the PR number");
     }
@@ -160,15 +439,23 @@ fn parse_args<'a>(args: &[&'a str]) -> anyhow::Result<Args<'a>> {
             continue;
         }
 
-        let Some((name, vers)) = arg.split_once(':') else {
-            bail!("package name and version must be separated by `:` - `{arg}`");
-        };
-        packages.push(Package { name, vers });
+        packages.push(parse_package(arg, namespace)?);
     }
 
     Ok(Args { packages, pr_nr })
 }
 
+fn parse_package<'a>(arg: &'a str, namespace: &'a str) -> anyhow::Result<Package<'a>> {
+    let Some((name, vers)) = arg.split_once(':') else {
+        bail!("package name and version must be separated by `:` - `{arg}`");
+    };
+    Ok(Package {
+        namespace,
+        name,
+        vers,
+    })
+}
+
 fn checkout_pr(args @ Args { pr_nr, .. }: &Args) -> anyhow::Result<()> {
     let branch_name = &args.branch_name();
 
@@ -225,25 +512,101 @@ fn checkout_branch(repo: &Repository, branch_name: &str) -> Result<(), git2::Err
     Ok(())
 }
 
-fn install_package(Package { name, vers }: &Package) -> anyhow::Result<PackageManifest> {
-    let package_dir = PathBuf::from_iter(["packages", "packages", "preview", name, vers]);
-    let mut target_dir = dirs::data_dir().expect("data dir");
-    target_dir.extend(["typst", "packages", "preview", name, vers]);
+fn install_package(
+    package @ Package {
+        namespace,
+        name,
+        vers,
+    }: &Package,
+    state: &mut State,
+    options: &Options,
+) -> anyhow::Result<PackageManifest> {
+    let package_dir = package.dir();
+    let mut target_dir = package_path(options);
+    target_dir.extend([namespace, name, vers]);
 
     println!(
         "install {ANSII_YELLOW}{}{ANSII_CLEAR}",
         package_dir.display()
     );
 
-    // Read manifest.
+    let manifest = read_manifest(&package_dir)?;
+    let files = bundle_files(&package_dir, &manifest)?;
+
+    // Delete existing package
+    if target_dir.exists() {
+        println!(
+            "remove existing package {ANSII_RED}{}{ANSII_CLEAR}",
+            target_dir.display()
+        );
+        std::fs::remove_dir_all(&target_dir).context("failed to remove existing package")?;
+    }
+
+    // The sandbox is removed together with the `test` directory.
+    if !options.sandbox {
+        state.installed.insert(format!("{namespace}/{name}/{vers}"));
+    }
+    copy_files(&package_dir, files, &target_dir)?;
+
+    Ok(manifest)
+}
+
+/// The package path that reviewed packages are installed into.
+fn package_path(options: &Options) -> PathBuf {
+    if options.sandbox {
+        return PathBuf::from_iter(["test", "sandbox", "packages"]);
+    }
+    let mut packages_dir = dirs::data_dir().expect("data dir");
+    packages_dir.extend(["typst", "packages"]);
+    packages_dir
+}
+
+/// Arguments that make `typst` use the isolated package path and cache, see
+/// [`Options::sandbox`].
+fn sandbox_args(options: &Options) -> Vec<OsString> {
+    if !options.sandbox {
+        return Vec::new();
+    }
+    let sandbox_dir = PathBuf::from_iter(["test", "sandbox"]);
+    vec![
+        "--package-path".into(),
+        sandbox_dir.join("packages").into(),
+        "--package-cache-path".into(),
+        sandbox_dir.join("cache").into(),
+    ]
+}
+
+fn read_manifest(package_dir: &Path) -> anyhow::Result<PackageManifest> {
     let manifest_path = package_dir.join("typst.toml");
     let manifest =
         std::fs::read_to_string(manifest_path).context("failed to read package manifest")?;
-    let manifest: PackageManifest =
-        toml::from_str(&manifest).context("failed to parse package manifest")?;
+    toml::from_str(&manifest).context("failed to parse package manifest")
+}
 
+/// Copy files from the package directory to the same relative path inside the target directory.
+fn copy_files(package_dir: &Path, files: Vec<PathBuf>, target_dir: &Path) -> anyhow::Result<()> {
+    for path in files {
+        let relative_path = path
+            .strip_prefix(package_dir)
+            .expect("path to be relative to package dir");
+        let target_path = target_dir.join(relative_path);
+
+        if let Some(parent) = target_path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("failed to create parent directory `{}`", parent.display())
+            })?;
+        }
+
+        std::fs::copy(&path, &target_path)
+            .with_context(|| format!("failed to copy to `{}`", target_path.display()))?;
+    }
+    Ok(())
+}
+
+/// Collect all files of the package that aren't excluded from the bundle.
+fn bundle_files(package_dir: &Path, manifest: &PackageManifest) -> anyhow::Result<Vec<PathBuf>> {
     // Build exclude overrides.
-    let mut builder = OverrideBuilder::new(&package_dir);
+    let mut builder = OverrideBuilder::new(package_dir);
     for exclude in manifest.package.exclude.iter() {
         if exclude.starts_with('!') {
             bail!("exclude globs cannot start with `!` - `{exclude}`");
@@ -252,47 +615,45 @@ fn install_package(Package { name, vers }: &Package) -> anyhow::Result<PackageMa
         let inverted = format!("!{exclude}");
         builder.add(&inverted).context("invalid exclude glob")?;
     }
-    let excludes = builder.build()?;
-    let walk = WalkBuilder::new(&package_dir).overrides(excludes).build();
-
-    // Delete existing package
-    if target_dir.exists() {
-        println!(
-            "remove existing package {ANSII_RED}{}{ANSII_CLEAR}",
-            target_dir.display()
-        );
-        std::fs::remove_dir_all(&target_dir).context("failed to remove existing package")?;
+    // The thumbnail is automatically excluded from the bundle.
+    if let Some(thumbnail) = manifest
+        .template
+        .as_ref()
+        .and_then(|t| t.thumbnail.as_ref())
+    {
+        let thumbnail = thumbnail.trim_start_matches("./");
+        builder
+            .add(&format!("!/{thumbnail}"))
+            .context("invalid thumbnail path")?;
     }
+    let excludes = builder.build()?;
+    let walk = WalkBuilder::new(package_dir).overrides(excludes).build();
 
-    // Copy files over
+    let mut files = Vec::new();
     for entry in walk.into_iter() {
         let entry = entry.context("failed to traverse")?;
-
-        let relative_path = entry
-            .path()
-            .strip_prefix(&package_dir)
-            .expect("path to be relative to package dir");
-        let target_path = target_dir.join(relative_path);
-
         if entry.file_type().is_some_and(|f| f.is_file()) {
-            if let Some(parent) = target_path.parent() {
-                std::fs::create_dir_all(parent).with_context(|| {
-                    format!("failed to create parent directory `{}`", parent.display())
-                })?;
-            }
-
-            std::fs::copy(entry.path(), &target_path)
-                .with_context(|| format!("failed to copy to `{}`", target_path.display()))?;
+            files.push(entry.into_path());
         }
     }
-
-    Ok(manifest)
+    Ok(files)
 }
 
 fn test_package(
-    package @ Package { name, .. }: &Package,
+    package @ Package { name, vers, .. }: &Package,
     manifest: &PackageManifest,
+    pr_nr: u32,
+    state: &mut State,
+    report: &mut Report,
+    log: &mut Log,
+    options: &Options,
 ) -> anyhow::Result<()> {
+    let typst_args = sandbox_args(options);
+    let preview_dir = PathBuf::from_iter(["test", "previews", name]);
+    if preview_dir.exists() {
+        std::fs::remove_dir_all(&preview_dir).context("failed to remove existing previews")?;
+    }
+
     if let Some(template) = &manifest.template {
         // Initialize template
         let spec = &package.spec();
@@ -307,31 +668,190 @@ fn test_package(
             std::fs::remove_dir_all(&template_dir).context("failed to remove existing template")?;
         }
 
-        run_command(
-            "typst",
-            ["init", spec, template_dir.to_str().expect("valid ASCII")],
-        )?;
+        let mut args = vec![OsString::from("init")];
+        args.extend(typst_args.iter().cloned());
+        args.extend([spec.into(), template_dir.clone().into()]);
+        run_captured(&options.typst, args, log)?;
 
-        // Try to compile template.
+        // Check the imports of the scaffolded project.
         let entrypoint = template_dir.join(template.entrypoint.as_str());
+        let findings = check::check_scaffold(package, &template_dir, &entrypoint)?;
+        render::print_findings(package, &findings);
+        report.add(package, findings);
+
+        // Try to compile template.
         let entrypoint_str = entrypoint.to_str().expect("valid utf-8");
         println!("compile template {ANSII_GREEN}{entrypoint_str}{ANSII_CLEAR}");
-        run_command("typst", ["compile", entrypoint_str])?;
+        // A fixed creation date makes the output reproducible, so it can be
+        // compared with the previous review.
+        let mut args = vec!["compile".into(), "--creation-timestamp".into(), "0".into()];
+        args.extend(typst_args.iter().cloned());
+        args.push(OsString::from(entrypoint_str));
+        run_captured(&options.typst, args, log)?;
+        render_previews(
+            &entrypoint,
+            None,
+            &typst_args,
+            &preview_dir,
+            "template",
+            log,
+            options,
+        )?;
 
-        // Open the PDF
+        // Compare the output with the previous review of this PR.
         let pdf = entrypoint.with_extension("pdf");
-        let pdf_str = pdf.to_str().expect("valid utf-8");
-        run_command("xdg-open", [pdf_str])?;
+        let pdf_bytes = std::fs::read(&pdf).context("failed to read compiled PDF")?;
+        let hash = state::pdf_hash(&pdf_bytes);
+        let package_state = state.package(pr_nr, name, vers);
+        if let Some(prev) = package_state.pdf_hash.replace(hash.clone()) {
+            let changed = prev != hash;
+            if changed {
+                println!("output {ANSII_YELLOW}changed{ANSII_CLEAR} since the last review");
+            } else {
+                println!("output {ANSII_GREEN}unchanged{ANSII_CLEAR} since the last review");
+            }
+            report.set_output_changed(package, changed);
+        }
+
+        // Archive the PDF next to the previews, since the project might be
+        // removed after testing, and open it from there.
+        let archived_pdf = preview_dir.join("template.pdf");
+        std::fs::write(&archived_pdf, pdf_bytes).context("failed to archive PDF")?;
+        run_command(&options.viewer, [&archived_pdf])?;
+    }
+
+    // Render examples, they aren't required to compile.
+    let package_dir = package.dir();
+    for example in example_files(&package_dir)? {
+        let prefix = example_prefix(&package_dir, &example);
+        if let Err(e) = render_previews(
+            &example,
+            Some(&package_dir),
+            &typst_args,
+            &preview_dir,
+            &prefix,
+            log,
+            options,
+        ) {
+            println!(
+                "{ANSII_RED}failed to render example{ANSII_CLEAR} `{}`: {e}",
+                example.display()
+            );
+        }
     }
 
     Ok(())
 }
 
-fn run_command<const N: usize>(cmd: &str, args: [&str; N]) -> anyhow::Result<()> {
-    let status = Command::new(cmd)
+/// Remove the scaffolded template project according to [`Options::keep`], the
+/// previews are always kept.
+fn remove_artifacts(
+    Package { name, .. }: &Package,
+    success: bool,
+    options: &Options,
+) -> anyhow::Result<()> {
+    let template_dir = PathBuf::from_iter(["test", name]);
+    let remove = match options.keep {
+        Keep::OnFailure => success,
+        Keep::Always => false,
+        Keep::Never => true,
+    };
+    if !remove || !template_dir.exists() {
+        return Ok(());
+    }
+    println!(
+        "remove template {ANSII_RED}{}{ANSII_CLEAR}",
+        template_dir.display()
+    );
+    std::fs::remove_dir_all(&template_dir).context("failed to remove template")
+}
+
+/// Collect `.typ` files inside `example` or `examples` directories.
+fn example_files(package_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in WalkBuilder::new(package_dir).build() {
+        let entry = entry.context("failed to traverse")?;
+        let path = entry.path();
+        let relative_path = path
+            .strip_prefix(package_dir)
+            .expect("path to be relative to package dir");
+        let in_example_dir = (relative_path.parent().into_iter())
+            .flat_map(|p| p.iter())
+            .any(|c| c == "example" || c == "examples");
+        if in_example_dir && path.extension().is_some_and(|e| e == "typ") {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The prefix of the preview images of an example, e.g. `examples-basic`.
+fn example_prefix(package_dir: &Path, example: &Path) -> String {
+    let relative_path = example
+        .strip_prefix(package_dir)
+        .expect("path to be relative to package dir")
+        .with_extension("");
+    (relative_path.iter())
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Render up to [`Options::preview_pages`] pages of a document to PNG images
+/// named `<prefix>-<page>.png`.
+fn render_previews(
+    input: &Path,
+    root: Option<&Path>,
+    typst_args: &[OsString],
+    preview_dir: &Path,
+    prefix: &str,
+    log: &mut Log,
+    options: &Options,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(preview_dir).context("failed to create preview directory")?;
+    let output = preview_dir.join(format!("{prefix}-{{0p}}.png"));
+    println!(
+        "render previews {ANSII_GREEN}{}{ANSII_CLEAR}",
+        output.display()
+    );
+
+    let mut args = vec![OsStr::new("compile"), input.as_os_str(), output.as_os_str()];
+    let pages = format!("1-{}", options.preview_pages);
+    args.extend([OsStr::new("--pages"), OsStr::new(&pages)]);
+    if let Some(root) = root {
+        args.extend([OsStr::new("--root"), root.as_os_str()]);
+    }
+    args.extend(typst_args.iter().map(OsString::as_os_str));
+    run_captured(&options.typst, args, log)
+}
+
+/// Build a command from a program followed by its arguments, for example
+/// `["mise", "exec", "typst@0.13", "--", "typst"]`.
+fn command(cmd: &[impl AsRef<OsStr>]) -> anyhow::Result<Command> {
+    let Some((program, args)) = cmd.split_first() else {
+        bail!("empty command");
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    Ok(command)
+}
+
+fn display_command(cmd: &[impl AsRef<OsStr>]) -> String {
+    (cmd.iter())
+        .map(|c| c.as_ref().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn run_command(
+    cmd: &[impl AsRef<OsStr>],
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+) -> anyhow::Result<()> {
+    let status = command(cmd)?
         .args(args)
         .status()
-        .expect("failed to execute process");
+        .with_context(|| format!("failed to execute `{}`", display_command(cmd)))?;
 
     if !status.success() {
         bail!("command failed");
@@ -340,15 +860,90 @@ fn run_command<const N: usize>(cmd: &str, args: [&str; N]) -> anyhow::Result<()>
     Ok(())
 }
 
-fn clean() -> anyhow::Result<()> {
-    let mut target_dir = dirs::data_dir().expect("data dir");
-    target_dir.extend(["typst", "packages", "preview"]);
-    clear_directory(&target_dir).context("failed to clean target directory")?;
+/// Output of spawned processes, labeled with the package it belongs to.
+struct Log {
+    label: String,
+    lines: Vec<String>,
+}
+
+impl Log {
+    fn new(Package { name, vers, .. }: &Package) -> Self {
+        Self {
+            label: format!("{name}:{vers}"),
+            lines: Vec::new(),
+        }
+    }
+}
+
+/// Run a command and capture its output instead of inheriting it, so it can
+/// be attributed to a package even when multiple packages are tested.
+fn run_captured(
+    cmd: &[impl AsRef<OsStr>],
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    log: &mut Log,
+) -> anyhow::Result<()> {
+    let output = command(cmd)?
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to execute `{}`", display_command(cmd)))?;
+
+    // The output isn't necessarily valid UTF-8, depending on the locale and
+    // the file names involved.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in stdout.lines().chain(stderr.lines()) {
+        let label = &log.label;
+        println!("{ANSII_BLUE}{label}{ANSII_CLEAR} {line}");
+        log.lines.push(format!("{label} {line}"));
+    }
+
+    if !output.status.success() {
+        bail!("command failed");
+    }
+
+    Ok(())
+}
+
+fn clean(options: &Options) -> anyhow::Result<()> {
+    let mut packages_dir = dirs::data_dir().expect("data dir");
+    packages_dir.extend(["typst", "packages"]);
+    let mut state = State::load()?;
+    if options.all {
+        let namespace_dir = packages_dir.join(&options.namespace);
+        clear_directory(&namespace_dir).context("failed to clean target directory")?;
+        let prefix = format!("{}/", options.namespace);
+        state.installed.retain(|p| !p.starts_with(&prefix));
+    } else {
+        remove_installed_packages(&packages_dir, &mut state)
+            .context("failed to clean target directory")?;
+    }
+    state.save()?;
     clear_directory("test".as_ref()).context("failed to clean target directory")?;
     remove_other_branches_and_pull_main().context("failed to clean branches")?;
     Ok(())
 }
 
+/// Only remove packages that were installed by this tool, and leave packages
+/// installed by other means alone.
+fn remove_installed_packages(packages_dir: &Path, state: &mut State) -> anyhow::Result<()> {
+    for installed in std::mem::take(&mut state.installed) {
+        let package_dir = packages_dir.join(&installed);
+        if !package_dir.exists() {
+            continue;
+        }
+        println!("remove {ANSII_RED}{}{ANSII_CLEAR}", package_dir.display());
+        std::fs::remove_dir_all(&package_dir).context("failed to remove directory")?;
+
+        // Remove the now empty package directory.
+        if let Some(parent) = package_dir.parent()
+            && std::fs::read_dir(parent).is_ok_and(|mut e| e.next().is_none())
+        {
+            std::fs::remove_dir(parent).context("failed to remove directory")?;
+        }
+    }
+    Ok(())
+}
+
 fn clear_directory(dir: &Path) -> anyhow::Result<()> {
     let Ok(entries) = std::fs::read_dir(dir) else {
         println!("directory wasn't found at: `{}`", dir.display());
@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, bail};
+use git2::{Oid, Repository};
+use typst_syntax::package::{PackageManifest, PackageVersion};
+
+use crate::check::{Finding, Severity};
+use crate::{ANSII_CLEAR, ANSII_RED, ANSII_YELLOW, Package, http};
+
+/// The minimum number of characters of a PR description, without the PR
+/// template, to count as a stated reason.
+const REASON_MIN_LEN: usize = 10;
+
+/// The author and description of a PR on GitHub.
+pub struct PullRequest {
+    pub author: String,
+    pub body: String,
+}
+
+impl PullRequest {
+    pub fn fetch(client: &http::Client, pr_nr: u32) -> anyhow::Result<Self> {
+        let repo = Repository::open("packages")?;
+        let origin = repo.find_remote("origin")?;
+        let Some(github_repo) = origin.url().and_then(github_repo) else {
+            bail!("`origin` isn't a GitHub repository");
+        };
+        let url = format!("https://api.github.com/repos/{github_repo}/pulls/{pr_nr}");
+        let text = client.get(&url)?;
+        let json: serde_json::Value =
+            serde_json::from_str(&text).context("failed to parse pull request")?;
+        let author = (json["user"]["login"].as_str())
+            .context("pull request has no author")?
+            .to_string();
+        let body = json["body"].as_str().unwrap_or_default().to_string();
+        Ok(Self { author, body })
+    }
+}
+
+/// Packages that are removed by the PR don't exist in the checkout anymore.
+pub fn is_removed(package: &Package) -> bool {
+    !package.dir().exists()
+}
+
+/// The commit the checked out PR is based on, where the removed versions
+/// still exist. `main` is fetched first, since the local `main` branch is only
+/// updated by `clean`.
+pub fn base_commit() -> anyhow::Result<Oid> {
+    let repo = Repository::open("packages")?;
+    let mut origin = repo.find_remote("origin")?;
+    let refspec = "+refs/heads/main:refs/remotes/origin/main";
+    println!("fetching {ANSII_YELLOW}main{ANSII_CLEAR}");
+    if let Err(e) = origin.fetch(&[refspec], None, None) {
+        println!("{ANSII_RED}failed to fetch `main`{ANSII_CLEAR}: {e}");
+    }
+
+    let head = repo.head()?.peel_to_commit()?.id();
+    let main = (repo.revparse_single("origin/main"))
+        .or_else(|_| repo.revparse_single("main"))?
+        .peel_to_commit()?
+        .id();
+    Ok(repo.merge_base(head, main).unwrap_or(main))
+}
+
+/// Read the manifest of a removed package from the base commit of the PR.
+fn removed_manifest(
+    Package {
+        namespace,
+        name,
+        vers,
+    }: &Package,
+    base: Option<Oid>,
+) -> anyhow::Result<PackageManifest> {
+    let Some(base) = base else {
+        bail!("the base commit of the PR is unknown");
+    };
+    let repo = Repository::open("packages")?;
+    let tree = repo.find_commit(base)?.tree()?;
+    let path = PathBuf::from_iter(["packages", namespace, name, vers, "typst.toml"]);
+    let Ok(entry) = tree.get_path(&path) else {
+        bail!("`{name}:{vers}` neither exists in the PR nor in its base commit `{base}`");
+    };
+    let blob = (entry.to_object(&repo)?)
+        .peel_to_blob()
+        .context("package manifest isn't a file")?;
+    let text = std::str::from_utf8(blob.content()).context("invalid utf-8 in manifest")?;
+    toml::from_str(text).context("failed to parse package manifest")
+}
+
+/// Check a removed or yanked package version.
+pub fn check_removal(
+    package @ Package { name, vers, .. }: &Package,
+    base: Option<Oid>,
+    pr: Option<&PullRequest>,
+) -> anyhow::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    println!("{name}:{vers} is removed");
+    let manifest = removed_manifest(package, base)
+        .inspect_err(|e| {
+            let message =
+                format!("failed to read the removed manifest, check the authors manually: {e}");
+            findings.push(Finding::new(message).severity(Severity::High));
+        })
+        .ok();
+
+    // Check whether this is the latest version.
+    let removed_vers: PackageVersion =
+        (vers.parse()).map_err(|e| anyhow::anyhow!("invalid version `{vers}`: {e}"))?;
+    let versions_dir = package.dir();
+    let versions_dir = versions_dir.parent().expect("version dir to have a parent");
+    let mut remaining = Vec::<PackageVersion>::new();
+    if let Ok(entries) = std::fs::read_dir(versions_dir) {
+        for entry in entries {
+            let entry = entry.context("failed to read entry")?;
+            if let Some(v) = entry.file_name().to_str().and_then(|v| v.parse().ok()) {
+                remaining.push(v);
+            }
+        }
+    }
+    match remaining.iter().max() {
+        None => {
+            let message = "removes the last remaining version, the package will disappear";
+            findings.push(Finding::new(message).severity(Severity::High));
+        }
+        Some(latest) if *latest < removed_vers => {
+            let message = format!("removes the latest version, `{latest}` will be the latest");
+            findings.push(Finding::new(message));
+        }
+        Some(latest) => {
+            let message = format!("removes an older version, the latest is `{latest}`");
+            findings.push(Finding::new(message).severity(Severity::Low));
+        }
+    }
+
+    let Some(pr) = pr else {
+        let message = "could not fetch the PR, check the author and the reason manually";
+        findings.push(Finding::new(message));
+        return Ok(findings);
+    };
+
+    // Check whether the PR author is one of the package authors. Authors may
+    // link their GitHub account as `<@login>` or `<https://github.com/login>`.
+    // A missing manifest was already reported above.
+    if let Some(manifest) = &manifest {
+        let logins = (manifest.package.authors.iter())
+            .filter_map(|a| github_login(a))
+            .collect::<Vec<_>>();
+        let author = &pr.author;
+        if logins.is_empty() {
+            let message = format!(
+                "no author links a GitHub account, check manually that `{author}` is one of the authors"
+            );
+            findings.push(Finding::new(message).severity(Severity::Low));
+        } else if !logins.iter().any(|l| l.eq_ignore_ascii_case(author)) {
+            let message = format!(
+                "PR author `{author}` isn't one of the package authors ({})",
+                logins.join(", ")
+            );
+            findings.push(Finding::new(message).severity(Severity::High));
+        }
+    }
+
+    if stated_reason(&pr.body).chars().count() < REASON_MIN_LEN {
+        let message = "the PR doesn't state a reason for the removal";
+        findings.push(Finding::new(message));
+    }
+
+    Ok(findings)
+}
+
+/// Strip the parts of the PR template, which are HTML comments and checklist
+/// items, from a PR description.
+fn stated_reason(body: &str) -> String {
+    let mut text = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("<!--") {
+        text.push_str(&rest[..start]);
+        rest = match rest[start..].find("-->") {
+            Some(end) => &rest[start + end + "-->".len()..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+
+    let is_checklist_item = |line: &str| {
+        let line = line.trim_start();
+        ["- [ ]", "- [x]", "- [X]", "* [ ]", "* [x]", "* [X]"]
+            .iter()
+            .any(|item| line.starts_with(item))
+    };
+    (text.lines())
+        .filter(|line| !is_checklist_item(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// The `owner/repo` of a GitHub remote URL, e.g. `https://github.com/typst/packages`
+/// or `git@github.com:typst/packages.git`.
+fn github_repo(url: &str) -> Option<&str> {
+    let path = [
+        "https://github.com/",
+        "ssh://git@github.com/",
+        "git@github.com:",
+    ]
+    .iter()
+    .find_map(|prefix| url.strip_prefix(prefix))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, repo) = path.split_once('/')?;
+    (!owner.is_empty() && !repo.is_empty() && !repo.contains('/')).then_some(path)
+}
+
+fn github_login(author: &str) -> Option<&str> {
+    let (_, link) = author.split_once('<')?;
+    let link = link.strip_suffix('>')?.trim();
+    let login = (link.strip_prefix('@'))
+        .or_else(|| link.strip_prefix("https://github.com/"))?
+        .trim_end_matches('/');
+    (!login.is_empty()).then_some(login)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_remote() {
+        let repo = Some("typst/packages");
+        assert_eq!(github_repo("https://github.com/typst/packages"), repo);
+        assert_eq!(github_repo("https://github.com/typst/packages.git"), repo);
+        assert_eq!(github_repo("git@github.com:typst/packages.git"), repo);
+        assert_eq!(github_repo("ssh://git@github.com/typst/packages"), repo);
+        assert_eq!(github_repo("https://gitlab.com/typst/packages"), None);
+        assert_eq!(github_repo("https://github.com/typst"), None);
+    }
+
+    #[test]
+    fn pr_template_is_no_reason() {
+        let body = "<!--\nPlease describe your changes.\n-->\n\n\
+                    - [x] I have read the guidelines\n\
+                    - [ ] I have tested the package\n";
+        assert_eq!(stated_reason(body), "");
+        assert_eq!(stated_reason(""), "");
+    }
+
+    #[test]
+    fn reason_outside_of_pr_template() {
+        let body = "<!-- Describe your changes. -->\n\
+                    The version has a bug that corrupts documents.\n\
+                    - [x] I have read the guidelines\n";
+        assert_eq!(
+            stated_reason(body),
+            "The version has a bug that corrupts documents."
+        );
+    }
+}
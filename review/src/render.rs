@@ -0,0 +1,695 @@
+use std::fmt::Write as _;
+
+use crate::check::Finding;
+use crate::compare::{Comparison, ManifestChange, PageChange};
+use crate::diff::PackageDiff;
+use crate::report::{PackageReport, Report};
+use crate::{ANSII_BLUE, ANSII_CLEAR, Package};
+
+/// Renders a [`Report`] or a [`Comparison`] into an output format.
+pub trait Renderer {
+    fn render(&self, report: &Report) -> String;
+
+    fn render_comparison(&self, comparison: &Comparison) -> String;
+}
+
+/// The formats the report can be written in.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "markdown" | "md" => Some(Format::Markdown),
+            "json" => Some(Format::Json),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Json => "json",
+            Format::Html => "html",
+        }
+    }
+
+    pub fn renderer(self) -> &'static dyn Renderer {
+        match self {
+            Format::Markdown => &Markdown,
+            Format::Json => &Json,
+            Format::Html => &Html,
+        }
+    }
+}
+
+pub fn print_findings(Package { name, vers, .. }: &Package, findings: &[Finding]) {
+    let mut buf = String::new();
+    write_findings(&mut buf, &format!("{name}:{vers}"), findings, true);
+    print!("{buf}");
+}
+
+fn write_findings(buf: &mut String, label: &str, findings: &[Finding], snippets: bool) {
+    if findings.is_empty() {
+        _ = writeln!(buf, "{label} no findings");
+    }
+    for Finding {
+        severity,
+        message,
+        location,
+    } in findings.iter()
+    {
+        let (color, severity) = (severity.color(), severity.label());
+        _ = writeln!(buf, "{color}{severity}{ANSII_CLEAR} {label} {message}");
+        if let Some(location) = location
+            && snippets
+        {
+            _ = writeln!(buf, "{}", location.snippet());
+        }
+    }
+}
+
+/// A summary of the report for the terminal, without snippets and output,
+/// since those were already printed while reviewing.
+pub struct Terminal;
+
+impl Renderer for Terminal {
+    fn render(&self, report: &Report) -> String {
+        let mut buf = String::new();
+        for PackageReport {
+            spec,
+            removed,
+            diff,
+            output_changed,
+            published_versions,
+            universe_url,
+            dependents,
+            findings,
+            ..
+        } in report.packages.iter()
+        {
+            let removed = if *removed { " (removed)" } else { "" };
+            _ = writeln!(buf, "{ANSII_BLUE}{spec}{ANSII_CLEAR}{removed}");
+            if let Some(PackageDiff { previous, changes }) = diff {
+                match previous {
+                    Some(previous) => {
+                        _ = writeln!(buf, "{} files changed since {previous}", changes.len())
+                    }
+                    None => _ = writeln!(buf, "first version with {} files", changes.len()),
+                }
+            }
+            if let Some(changed) = output_changed {
+                _ = writeln!(
+                    buf,
+                    "output {} since the last review",
+                    changed_str(*changed)
+                );
+            }
+            match published_versions.as_deref() {
+                Some([]) => _ = writeln!(buf, "new package, no version is published yet"),
+                Some(versions) => _ = writeln!(buf, "published versions {}", versions.join(", ")),
+                None => (),
+            }
+            if let Some(url) = universe_url {
+                _ = writeln!(buf, "Universe page {url}");
+            }
+            if let Some(dependents) = dependents {
+                _ = writeln!(buf, "used by {}", dependents_str(dependents, |d| d.into()));
+            }
+            write_findings(&mut buf, spec, findings, false);
+        }
+        buf
+    }
+
+    fn render_comparison(&self, comparison: &Comparison) -> String {
+        let Comparison {
+            old,
+            new,
+            manifest_changes,
+            file_changes,
+            identical_pages,
+            page_changes,
+            ..
+        } = comparison;
+        let mut buf = String::new();
+        _ = writeln!(
+            buf,
+            "{ANSII_BLUE}{old}{ANSII_CLEAR} -> {ANSII_BLUE}{new}{ANSII_CLEAR}"
+        );
+        _ = writeln!(buf, "{} manifest fields changed", manifest_changes.len());
+        _ = writeln!(buf, "{} files changed", file_changes.len());
+        _ = writeln!(
+            buf,
+            "{} pages changed, {identical_pages} identical",
+            page_changes.len()
+        );
+        buf
+    }
+}
+
+pub struct Markdown;
+
+impl Renderer for Markdown {
+    fn render(&self, report: &Report) -> String {
+        let mut buf = String::new();
+        _ = writeln!(buf, "# Review of PR #{}", report.pr_nr);
+        for PackageReport {
+            spec,
+            removed,
+            diff,
+            output_changed,
+            published_versions,
+            universe_url,
+            dependents,
+            findings,
+            log,
+        } in report.packages.iter()
+        {
+            _ = writeln!(buf);
+            if *removed {
+                _ = writeln!(buf, "## Removal of `{spec}`");
+            } else {
+                _ = writeln!(buf, "## `{spec}`");
+            }
+            _ = writeln!(buf);
+            match diff {
+                Some(PackageDiff {
+                    previous: Some(previous),
+                    changes,
+                }) => {
+                    _ = writeln!(buf, "### Changes since `{previous}`");
+                    _ = writeln!(buf);
+                    if changes.is_empty() {
+                        _ = writeln!(buf, "No changes.");
+                    }
+                    for (change, path) in changes.iter() {
+                        _ = writeln!(buf, "- {} `{path}`", change.as_str());
+                    }
+                    _ = writeln!(buf);
+                }
+                Some(PackageDiff {
+                    previous: None,
+                    changes,
+                }) => {
+                    _ = writeln!(buf, "First version with {} files.", changes.len());
+                    _ = writeln!(buf);
+                }
+                None => (),
+            }
+            if let Some(changed) = output_changed {
+                let changed = changed_str(*changed);
+                _ = writeln!(buf, "Template output {changed} since the last review.");
+                _ = writeln!(buf);
+            }
+            match published_versions.as_deref() {
+                Some([]) => {
+                    _ = writeln!(buf, "New package, no version is published yet.");
+                    _ = writeln!(buf);
+                }
+                Some(versions) => {
+                    let versions = (versions.iter())
+                        .map(|v| format!("`{v}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    _ = writeln!(buf, "Published versions: {versions}.");
+                    _ = writeln!(buf);
+                }
+                None => (),
+            }
+            if let Some(url) = universe_url {
+                _ = writeln!(buf, "Universe page: <{url}>");
+                _ = writeln!(buf);
+            }
+            if let Some(dependents) = dependents {
+                let dependents = dependents_str(dependents, |d| format!("`{d}`"));
+                _ = writeln!(buf, "Used by {dependents}.");
+                _ = writeln!(buf);
+            }
+            _ = writeln!(buf, "### Findings");
+            _ = writeln!(buf);
+            if findings.is_empty() {
+                _ = writeln!(buf, "No findings.");
+            }
+            for Finding {
+                severity,
+                message,
+                location,
+            } in findings.iter()
+            {
+                _ = writeln!(buf, "- **{}**: {message}", severity.label());
+                if let Some(location) = location {
+                    _ = writeln!(buf, "  ```");
+                    for line in location.snippet().lines() {
+                        _ = writeln!(buf, "  {line}");
+                    }
+                    _ = writeln!(buf, "  ```");
+                }
+            }
+            if !log.is_empty() {
+                _ = writeln!(buf);
+                _ = writeln!(buf, "### Output");
+                _ = writeln!(buf);
+                _ = writeln!(buf, "```");
+                for line in log.iter() {
+                    _ = writeln!(buf, "{line}");
+                }
+                _ = writeln!(buf, "```");
+            }
+        }
+        buf
+    }
+
+    fn render_comparison(&self, comparison: &Comparison) -> String {
+        let Comparison {
+            old,
+            new,
+            manifest_changes,
+            file_changes,
+            text_diffs,
+            identical_pages,
+            page_changes,
+            log,
+        } = comparison;
+        let mut buf = String::new();
+        _ = writeln!(buf, "# Comparison of `{old}` and `{new}`");
+
+        _ = writeln!(buf, "\n## Manifest\n");
+        if manifest_changes.is_empty() {
+            _ = writeln!(buf, "No changes.");
+        }
+        for ManifestChange { key, old, new } in manifest_changes.iter() {
+            match (old, new) {
+                (Some(a), Some(b)) => _ = writeln!(buf, "- `{key}`: `{a}` -> `{b}`"),
+                (Some(a), None) => _ = writeln!(buf, "- `{key}`: removed `{a}`"),
+                (None, Some(b)) => _ = writeln!(buf, "- `{key}`: added `{b}`"),
+                (None, None) => (),
+            }
+        }
+
+        _ = writeln!(buf, "\n## Files\n");
+        if file_changes.is_empty() {
+            _ = writeln!(buf, "No changes.");
+        }
+        for (change, path) in file_changes.iter() {
+            _ = writeln!(buf, "- {} `{path}`", change.as_str());
+        }
+
+        if !text_diffs.is_empty() {
+            _ = writeln!(buf, "\n## Diffs");
+        }
+        for (path, diff) in text_diffs.iter() {
+            // The fence must be longer than any backtick sequence inside of
+            // it, like raw blocks in typst files.
+            let longest = (diff.split(|c| c != '`')).map(str::len).max().unwrap_or(0);
+            let fence = "`".repeat(longest.max(2) + 1);
+            _ = writeln!(buf, "\n### `{path}`\n");
+            _ = writeln!(buf, "{fence}diff");
+            _ = write!(buf, "{diff}");
+            _ = writeln!(buf, "{fence}");
+        }
+
+        _ = writeln!(buf, "\n## Pages\n");
+        _ = writeln!(buf, "{identical_pages} identical pages.");
+        if !page_changes.is_empty() {
+            _ = writeln!(buf, "\n| Page | `{old}` | `{new}` |");
+            _ = writeln!(buf, "| --- | --- | --- |");
+            let image = |path: &Option<String>| match path {
+                Some(path) => format!("![]({path})"),
+                None => "missing".to_string(),
+            };
+            for PageChange {
+                prefix,
+                page,
+                status,
+                old,
+                new,
+            } in page_changes.iter()
+            {
+                _ = writeln!(
+                    buf,
+                    "| {prefix} {page} ({status}) | {} | {} |",
+                    image(old),
+                    image(new)
+                );
+            }
+        }
+
+        if !log.is_empty() {
+            _ = writeln!(buf, "\n## Output\n");
+            _ = writeln!(buf, "```");
+            for line in log.iter() {
+                _ = writeln!(buf, "{line}");
+            }
+            _ = writeln!(buf, "```");
+        }
+        buf
+    }
+}
+
+pub struct Json;
+
+impl Renderer for Json {
+    fn render(&self, report: &Report) -> String {
+        let packages = (report.packages.iter())
+            .map(|package| {
+                let diff = package.diff.as_ref().map(|diff| {
+                    let changes = (diff.changes.iter())
+                        .map(|(change, path)| {
+                            serde_json::json!({ "change": change.as_str(), "path": path })
+                        })
+                        .collect::<Vec<_>>();
+                    serde_json::json!({ "previous": diff.previous, "changes": changes })
+                });
+                let findings = (package.findings.iter())
+                    .map(|finding| {
+                        let location = finding.location.as_ref().map(|location| {
+                            serde_json::json!({
+                                "path": location.path,
+                                "line": location.line,
+                                "column": location.column,
+                                "snippet": location.snippet(),
+                            })
+                        });
+                        serde_json::json!({
+                            "severity": finding.severity.as_str(),
+                            "message": finding.message,
+                            "location": location,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::json!({
+                    "spec": package.spec,
+                    "removed": package.removed,
+                    "diff": diff,
+                    "output_changed": package.output_changed,
+                    "published_versions": package.published_versions,
+                    "universe_url": package.universe_url,
+                    "dependents": package.dependents,
+                    "findings": findings,
+                    "output": package.log,
+                })
+            })
+            .collect::<Vec<_>>();
+        let json = serde_json::json!({ "pr": report.pr_nr, "packages": packages });
+        serde_json::to_string_pretty(&json).expect("JSON values to serialize")
+    }
+
+    fn render_comparison(&self, comparison: &Comparison) -> String {
+        let manifest_changes = (comparison.manifest_changes.iter())
+            .map(|change| {
+                serde_json::json!({ "key": change.key, "old": change.old, "new": change.new })
+            })
+            .collect::<Vec<_>>();
+        let file_changes = (comparison.file_changes.iter())
+            .map(|(change, path)| serde_json::json!({ "change": change.as_str(), "path": path }))
+            .collect::<Vec<_>>();
+        let text_diffs = (comparison.text_diffs.iter())
+            .map(|(path, diff)| serde_json::json!({ "path": path, "diff": diff }))
+            .collect::<Vec<_>>();
+        let page_changes = (comparison.page_changes.iter())
+            .map(|change| {
+                serde_json::json!({
+                    "prefix": change.prefix,
+                    "page": change.page,
+                    "status": change.status,
+                    "old": change.old,
+                    "new": change.new,
+                })
+            })
+            .collect::<Vec<_>>();
+        let json = serde_json::json!({
+            "old": comparison.old,
+            "new": comparison.new,
+            "manifest_changes": manifest_changes,
+            "file_changes": file_changes,
+            "text_diffs": text_diffs,
+            "identical_pages": comparison.identical_pages,
+            "page_changes": page_changes,
+            "output": comparison.log,
+        });
+        serde_json::to_string_pretty(&json).expect("JSON values to serialize")
+    }
+}
+
+pub struct Html;
+
+impl Renderer for Html {
+    fn render(&self, report: &Report) -> String {
+        let mut buf = String::new();
+        let title = format!("Review of PR #{}", report.pr_nr);
+        _ = writeln!(buf, "<!DOCTYPE html>");
+        _ = writeln!(buf, "<html>");
+        _ = writeln!(buf, "<head>");
+        _ = writeln!(buf, "<meta charset=\"utf-8\">");
+        _ = writeln!(buf, "<title>{title}</title>");
+        _ = writeln!(buf, "</head>");
+        _ = writeln!(buf, "<body>");
+        _ = writeln!(buf, "<h1>{title}</h1>");
+        for PackageReport {
+            spec,
+            removed,
+            diff,
+            output_changed,
+            published_versions,
+            universe_url,
+            dependents,
+            findings,
+            log,
+        } in report.packages.iter()
+        {
+            let spec = escape(spec);
+            if *removed {
+                _ = writeln!(buf, "<h2>Removal of <code>{spec}</code></h2>");
+            } else {
+                _ = writeln!(buf, "<h2><code>{spec}</code></h2>");
+            }
+            match diff {
+                Some(PackageDiff {
+                    previous: Some(previous),
+                    changes,
+                }) => {
+                    let previous = escape(previous);
+                    _ = writeln!(buf, "<h3>Changes since <code>{previous}</code></h3>");
+                    if changes.is_empty() {
+                        _ = writeln!(buf, "<p>No changes.</p>");
+                    } else {
+                        _ = writeln!(buf, "<ul>");
+                        for (change, path) in changes.iter() {
+                            let (change, path) = (change.as_str(), escape(path));
+                            _ = writeln!(buf, "<li>{change} <code>{path}</code></li>");
+                        }
+                        _ = writeln!(buf, "</ul>");
+                    }
+                }
+                Some(PackageDiff {
+                    previous: None,
+                    changes,
+                }) => {
+                    _ = writeln!(buf, "<p>First version with {} files.</p>", changes.len());
+                }
+                None => (),
+            }
+            if let Some(changed) = output_changed {
+                let changed = changed_str(*changed);
+                _ = writeln!(
+                    buf,
+                    "<p>Template output {changed} since the last review.</p>"
+                );
+            }
+            match published_versions.as_deref() {
+                Some([]) => _ = writeln!(buf, "<p>New package, no version is published yet.</p>"),
+                Some(versions) => {
+                    let versions = (versions.iter())
+                        .map(|v| format!("<code>{}</code>", escape(v)))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    _ = writeln!(buf, "<p>Published versions: {versions}.</p>");
+                }
+                None => (),
+            }
+            if let Some(url) = universe_url {
+                let url = escape(url);
+                _ = writeln!(buf, "<p>Universe page: <a href=\"{url}\">{url}</a></p>");
+            }
+            if let Some(dependents) = dependents {
+                let dependents =
+                    dependents_str(dependents, |d| format!("<code>{}</code>", escape(d)));
+                _ = writeln!(buf, "<p>Used by {dependents}.</p>");
+            }
+            _ = writeln!(buf, "<h3>Findings</h3>");
+            if findings.is_empty() {
+                _ = writeln!(buf, "<p>No findings.</p>");
+            } else {
+                _ = writeln!(buf, "<ul>");
+                for Finding {
+                    severity,
+                    message,
+                    location,
+                } in findings.iter()
+                {
+                    let (severity, message) = (severity.label(), escape(message));
+                    _ = write!(buf, "<li><strong>{severity}</strong>: {message}");
+                    if let Some(location) = location {
+                        _ = write!(buf, "<pre>{}</pre>", escape(&location.snippet()));
+                    }
+                    _ = writeln!(buf, "</li>");
+                }
+                _ = writeln!(buf, "</ul>");
+            }
+            if !log.is_empty() {
+                _ = writeln!(buf, "<h3>Output</h3>");
+                _ = writeln!(buf, "<pre>{}</pre>", escape(&log.join("\n")));
+            }
+        }
+        _ = writeln!(buf, "</body>");
+        _ = writeln!(buf, "</html>");
+        buf
+    }
+
+    fn render_comparison(&self, comparison: &Comparison) -> String {
+        let Comparison {
+            old,
+            new,
+            manifest_changes,
+            file_changes,
+            text_diffs,
+            identical_pages,
+            page_changes,
+            log,
+        } = comparison;
+        let (old, new) = (escape(old), escape(new));
+        let mut buf = String::new();
+        _ = writeln!(buf, "<!DOCTYPE html>");
+        _ = writeln!(buf, "<html>");
+        _ = writeln!(buf, "<head>");
+        _ = writeln!(buf, "<meta charset=\"utf-8\">");
+        _ = writeln!(buf, "<title>Comparison of {old} and {new}</title>");
+        _ = writeln!(buf, "</head>");
+        _ = writeln!(buf, "<body>");
+        _ = writeln!(
+            buf,
+            "<h1>Comparison of <code>{old}</code> and <code>{new}</code></h1>"
+        );
+
+        _ = writeln!(buf, "<h2>Manifest</h2>");
+        if manifest_changes.is_empty() {
+            _ = writeln!(buf, "<p>No changes.</p>");
+        } else {
+            _ = writeln!(buf, "<ul>");
+            for ManifestChange { key, old, new } in manifest_changes.iter() {
+                let value = |v: &Option<String>| match v {
+                    Some(v) => format!("<code>{}</code>", escape(v)),
+                    None => "none".to_string(),
+                };
+                _ = writeln!(
+                    buf,
+                    "<li><code>{}</code>: {} -&gt; {}</li>",
+                    escape(key),
+                    value(old),
+                    value(new)
+                );
+            }
+            _ = writeln!(buf, "</ul>");
+        }
+
+        _ = writeln!(buf, "<h2>Files</h2>");
+        if file_changes.is_empty() {
+            _ = writeln!(buf, "<p>No changes.</p>");
+        } else {
+            _ = writeln!(buf, "<ul>");
+            for (change, path) in file_changes.iter() {
+                let (change, path) = (change.as_str(), escape(path));
+                _ = writeln!(buf, "<li>{change} <code>{path}</code></li>");
+            }
+            _ = writeln!(buf, "</ul>");
+        }
+
+        if !text_diffs.is_empty() {
+            _ = writeln!(buf, "<h2>Diffs</h2>");
+        }
+        for (path, diff) in text_diffs.iter() {
+            _ = writeln!(buf, "<h3><code>{}</code></h3>", escape(path));
+            _ = writeln!(buf, "<pre>{}</pre>", escape(diff));
+        }
+
+        _ = writeln!(buf, "<h2>Pages</h2>");
+        _ = writeln!(buf, "<p>{identical_pages} identical pages.</p>");
+        if !page_changes.is_empty() {
+            _ = writeln!(buf, "<table>");
+            _ = writeln!(
+                buf,
+                "<tr><th>Page</th><th><code>{old}</code></th><th><code>{new}</code></th></tr>"
+            );
+            let image = |path: &Option<String>| match path {
+                Some(path) => format!("<img src=\"{}\" width=\"300\">", escape(path)),
+                None => "missing".to_string(),
+            };
+            for PageChange {
+                prefix,
+                page,
+                status,
+                old,
+                new,
+            } in page_changes.iter()
+            {
+                _ = writeln!(
+                    buf,
+                    "<tr><td>{} {page} ({status})</td><td>{}</td><td>{}</td></tr>",
+                    escape(prefix),
+                    image(old),
+                    image(new)
+                );
+            }
+            _ = writeln!(buf, "</table>");
+        }
+
+        if !log.is_empty() {
+            _ = writeln!(buf, "<h2>Output</h2>");
+            _ = writeln!(buf, "<pre>{}</pre>", escape(&log.join("\n")));
+        }
+        _ = writeln!(buf, "</body>");
+        _ = writeln!(buf, "</html>");
+        buf
+    }
+}
+
+/// Describe the dependents of a package, only the first few are listed.
+pub fn dependents_str(dependents: &[String], name: impl Fn(&str) -> String) -> String {
+    const MAX_LISTED: usize = 10;
+
+    let mut list = (dependents.iter().take(MAX_LISTED))
+        .map(|d| name(d))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if dependents.len() > MAX_LISTED {
+        list.push_str(", ...");
+    }
+    match dependents.len() {
+        0 => "no other package in the local checkout".to_string(),
+        1 => format!("1 other package in the local checkout: {list}"),
+        n => format!("{n} other packages in the local checkout: {list}"),
+    }
+}
+
+fn changed_str(changed: bool) -> &'static str {
+    if changed { "changed" } else { "unchanged" }
+}
+
+fn escape(text: &str) -> String {
+    let mut buf = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            c => buf.push(c),
+        }
+    }
+    buf
+}
@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::check::{Finding, Severity};
+use crate::diff::PackageDiff;
+use crate::render::Format;
+use crate::{ANSII_CLEAR, ANSII_GREEN, Package};
+
+pub const REPORT_DIR: &str = "test";
+
+/// Findings of all reviewed packages, which are rendered by a
+/// [`Renderer`](crate::render::Renderer).
+pub struct Report {
+    pub pr_nr: u32,
+    pub packages: Vec<PackageReport>,
+}
+
+pub struct PackageReport {
+    pub spec: String,
+    /// Whether the PR removes this package version.
+    pub removed: bool,
+    pub diff: Option<PackageDiff>,
+    /// Whether the compiled template changed since the last review of the PR,
+    /// `None` if it wasn't reviewed before.
+    pub output_changed: Option<bool>,
+    /// The versions published on Typst Universe, `None` if they weren't
+    /// fetched.
+    pub published_versions: Option<Vec<String>>,
+    /// The Universe page, if the package is published.
+    pub universe_url: Option<String>,
+    /// Other packages in the local checkout that import the package, `None`
+    /// if it isn't an update.
+    pub dependents: Option<Vec<String>>,
+    pub findings: Vec<Finding>,
+    /// Captured output of the spawned `typst` processes.
+    pub log: Vec<String>,
+}
+
+impl Report {
+    pub fn new(pr_nr: u32) -> Self {
+        Self {
+            pr_nr,
+            packages: Vec::new(),
+        }
+    }
+
+    fn package(&mut self, package: &Package) -> &mut PackageReport {
+        let spec = package.spec();
+        let pos = self.packages.iter().position(|p| p.spec == spec);
+        let pos = pos.unwrap_or_else(|| {
+            self.packages.push(PackageReport {
+                spec,
+                removed: false,
+                diff: None,
+                output_changed: None,
+                published_versions: None,
+                universe_url: None,
+                dependents: None,
+                findings: Vec::new(),
+                log: Vec::new(),
+            });
+            self.packages.len() - 1
+        });
+        &mut self.packages[pos]
+    }
+
+    pub fn add(&mut self, package: &Package, findings: Vec<Finding>) {
+        self.package(package).findings.extend(findings);
+    }
+
+    pub fn add_log(&mut self, package: &Package, lines: Vec<String>) {
+        self.package(package).log.extend(lines);
+    }
+
+    /// Whether the package has findings that need attention, i.e. warnings or
+    /// important findings.
+    pub fn needs_attention(&self, package: &Package) -> bool {
+        let spec = package.spec();
+        (self.packages.iter())
+            .filter(|p| p.spec == spec)
+            .flat_map(|p| p.findings.iter())
+            .any(|f| f.severity >= Severity::Medium)
+    }
+
+    pub fn set_removed(&mut self, package: &Package) {
+        self.package(package).removed = true;
+    }
+
+    pub fn set_output_changed(&mut self, package: &Package, changed: bool) {
+        self.package(package).output_changed = Some(changed);
+    }
+
+    pub fn set_published(
+        &mut self,
+        package: &Package,
+        versions: Vec<String>,
+        universe_url: Option<String>,
+    ) {
+        let package = self.package(package);
+        package.published_versions = Some(versions);
+        package.universe_url = universe_url;
+    }
+
+    pub fn set_dependents(&mut self, package: &Package, dependents: Vec<String>) {
+        self.package(package).dependents = Some(dependents);
+    }
+
+    pub fn set_diff(&mut self, package: &Package, diff: PackageDiff) {
+        self.package(package).diff = Some(diff);
+    }
+
+    pub fn write(&self, format: Format) -> anyhow::Result<()> {
+        let path = Path::new(REPORT_DIR).join(format!("report.{}", format.extension()));
+        println!("write report {ANSII_GREEN}{}{ANSII_CLEAR}", path.display());
+        let text = format.renderer().render(self);
+        std::fs::write(path, text).context("failed to write report")
+    }
+}
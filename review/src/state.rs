@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+const STATE_PATH: &str = "state.toml";
+
+/// Persistent state that is kept between runs.
+#[derive(Default, Serialize, Deserialize)]
+pub struct State {
+    /// Packages installed by this tool as `namespace/name/version` paths
+    /// relative to the local package directory.
+    #[serde(default)]
+    pub installed: BTreeSet<String>,
+    /// Reviewed PRs by their number.
+    #[serde(default)]
+    pub prs: BTreeMap<String, PrState>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct PrState {
+    /// Reviewed packages by their `name:version`.
+    #[serde(default)]
+    pub packages: BTreeMap<String, PackageState>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct PackageState {
+    /// The hash of the compiled template PDF, see [`pdf_hash`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pdf_hash: Option<String>,
+}
+
+impl State {
+    pub fn load() -> anyhow::Result<Self> {
+        if !Path::new(STATE_PATH).exists() {
+            return Ok(State::default());
+        }
+        let text = std::fs::read_to_string(STATE_PATH).context("failed to read state")?;
+        toml::from_str(&text).context("failed to parse state")
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let text = toml::to_string(self).context("failed to serialize state")?;
+        std::fs::write(STATE_PATH, text).context("failed to write state")
+    }
+
+    pub fn package(&mut self, pr_nr: u32, name: &str, vers: &str) -> &mut PackageState {
+        let pr = self.prs.entry(pr_nr.to_string()).or_default();
+        pr.packages.entry(format!("{name}:{vers}")).or_default()
+    }
+}
+
+/// PDF metadata that changes on every compilation.
+const VOLATILE_PDF_FIELDS: &[(&[u8], &[u8])] = &[
+    (b"/CreationDate", b")"),
+    (b"/ModDate", b")"),
+    (b"/ID", b"]"),
+    (b"<xmp:CreateDate>", b"</xmp:CreateDate>"),
+    (b"<xmp:ModifyDate>", b"</xmp:ModifyDate>"),
+    (b"<xmp:MetadataDate>", b"</xmp:MetadataDate>"),
+    (b"<xmpMM:DocumentID>", b"</xmpMM:DocumentID>"),
+    (b"<xmpMM:InstanceID>", b"</xmpMM:InstanceID>"),
+];
+
+/// Hash the content of a PDF, ignoring volatile metadata like timestamps.
+pub fn pdf_hash(pdf: &[u8]) -> String {
+    // 64-bit FNV-1a, which is stable across runs and platforms.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut i = 0;
+    'outer: while i < pdf.len() {
+        for (start, end) in VOLATILE_PDF_FIELDS.iter() {
+            if pdf[i..].starts_with(start)
+                && let Some(len) = find(&pdf[i + start.len()..], end)
+            {
+                i += start.len() + len + end.len();
+                continue 'outer;
+            }
+        }
+        hash ^= pdf[i] as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+        i += 1;
+    }
+    format!("{hash:016x}")
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pdf(creation_date: &str, id: &str, xmp_date: &str, content: &str) -> Vec<u8> {
+        format!(
+            "%PDF-1.7\n\
+             1 0 obj\n<< /Producer (Typst) /CreationDate (D:{creation_date}) >>\nendobj\n\
+             2 0 obj\n<xmp:CreateDate>{xmp_date}</xmp:CreateDate>\
+             <xmp:ModifyDate>{xmp_date}</xmp:ModifyDate>\nendobj\n\
+             3 0 obj\n<< /Length 7 >>\nstream\n{content}\nendstream\nendobj\n\
+             trailer\n<< /ID [<{id}> <{id}>] >>\n%%EOF\n"
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn volatile_metadata_is_ignored() {
+        let hash = pdf_hash(&pdf(
+            "20240101120000Z",
+            "0123abcd",
+            "2024-01-01T12:00:00Z",
+            "BT (x) ET",
+        ));
+        let creation_date = pdf(
+            "20250505080910Z",
+            "0123abcd",
+            "2024-01-01T12:00:00Z",
+            "BT (x) ET",
+        );
+        assert_eq!(pdf_hash(&creation_date), hash);
+        let id = pdf(
+            "20240101120000Z",
+            "ffff0000",
+            "2024-01-01T12:00:00Z",
+            "BT (x) ET",
+        );
+        assert_eq!(pdf_hash(&id), hash);
+        let xmp_date = pdf(
+            "20240101120000Z",
+            "0123abcd",
+            "2025-05-05T08:09:10Z",
+            "BT (x) ET",
+        );
+        assert_eq!(pdf_hash(&xmp_date), hash);
+    }
+
+    #[test]
+    fn content_changes_are_detected() {
+        let a = pdf(
+            "20240101120000Z",
+            "0123abcd",
+            "2024-01-01T12:00:00Z",
+            "BT (x) ET",
+        );
+        let b = pdf(
+            "20240101120000Z",
+            "0123abcd",
+            "2024-01-01T12:00:00Z",
+            "BT (y) ET",
+        );
+        assert_ne!(pdf_hash(&a), pdf_hash(&b));
+    }
+}
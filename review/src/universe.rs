@@ -0,0 +1,90 @@
+use anyhow::Context;
+use typst_syntax::package::PackageVersion;
+
+use crate::check::Finding;
+use crate::{ANSII_BLUE, ANSII_CLEAR, Package, http};
+
+/// The published packages of a namespace.
+pub struct Index {
+    packages: Vec<(String, PackageVersion)>,
+}
+
+impl Index {
+    pub fn fetch(client: &http::Client, namespace: &str) -> anyhow::Result<Self> {
+        let url = format!("https://packages.typst.org/{namespace}/index.json");
+        let text = client.get(&url)?;
+        let json: serde_json::Value =
+            serde_json::from_str(&text).context("failed to parse package index")?;
+
+        let entries = json.as_array().context("package index isn't an array")?;
+        let mut packages = Vec::with_capacity(entries.len());
+        for entry in entries.iter() {
+            let (Some(name), Some(vers)) = (entry["name"].as_str(), entry["version"].as_str())
+            else {
+                continue;
+            };
+            let Ok(vers) = vers.parse() else {
+                continue;
+            };
+            packages.push((name.to_string(), vers));
+        }
+        Ok(Self { packages })
+    }
+
+    /// All published versions of a package in ascending order.
+    pub fn versions(&self, name: &str) -> Vec<PackageVersion> {
+        let mut versions = (self.packages.iter())
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| *v)
+            .collect::<Vec<_>>();
+        versions.sort();
+        versions
+    }
+}
+
+/// The Universe page only exists for packages in the `preview` namespace.
+pub fn page_url(
+    Package {
+        namespace, name, ..
+    }: &Package,
+) -> Option<String> {
+    (*namespace == "preview").then(|| format!("https://typst.app/universe/package/{name}"))
+}
+
+pub fn print_published(versions: &[String], url: Option<&str>) {
+    if versions.is_empty() {
+        println!("new package, no version is published yet");
+        return;
+    }
+    println!(
+        "published versions {ANSII_BLUE}{}{ANSII_CLEAR}",
+        versions.join(", ")
+    );
+    if let Some(url) = url {
+        println!("Universe page {ANSII_BLUE}{url}{ANSII_CLEAR}");
+    }
+}
+
+/// Compare the submitted package against the published versions.
+pub fn check_package(Package { name, vers, .. }: &Package, index: &Index) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let versions = index.versions(name);
+
+    match vers.parse::<PackageVersion>() {
+        Ok(v) if versions.contains(&v) => {
+            findings.push(Finding::new(format!(
+                "version {vers} is already published, bump the version"
+            )));
+        }
+        Ok(v) if versions.last().is_some_and(|latest| v < *latest) => {
+            findings.push(Finding::new(format!(
+                "version {vers} is older than the latest published version {}",
+                versions.last().unwrap(),
+            )));
+        }
+        Ok(_) => (),
+        Err(e) => findings.push(Finding::new(format!("version `{vers}` is invalid: {e}"))),
+    }
+
+    findings
+}